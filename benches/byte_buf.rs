@@ -0,0 +1,32 @@
+//! Decoding 1 MB of bytes as a plain `Vec<u8>` and as a `serde_bytes::ByteBuf`
+//!
+//! Run with `cargo bench --bench byte_buf`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pinecone::{from_bytes, to_vec};
+use serde_bytes::ByteBuf;
+
+const ROUNDS: u32 = 50;
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let output: Vec<u8> = to_vec(&input).unwrap();
+    // Both types have the same encoding
+    assert_eq!(output, to_vec(&ByteBuf::from(input.clone())).unwrap());
+
+    let seq = time(|| from_bytes::<Vec<u8>>(black_box(&output)).unwrap());
+    let buf = time(|| from_bytes::<ByteBuf>(black_box(&output)).unwrap());
+
+    println!("Vec<u8>: {:?} per MB", seq);
+    println!("ByteBuf: {:?} per MB", buf);
+}
@@ -0,0 +1,40 @@
+//! Decoding 1M words as a plain `Vec<u32>` and with `compat::le_slice`
+//!
+//! Run with `cargo bench --bench le_slice`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pinecone::{from_bytes, to_vec};
+use serde::{Deserialize, Serialize};
+
+const ROUNDS: u32 = 20;
+
+#[derive(Serialize, Deserialize)]
+struct Samples {
+    id: u8,
+    #[serde(with = "pinecone::compat::le_slice")]
+    values: Vec<u32>,
+}
+
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let values: Vec<u32> = (0..1_000_000u32)
+        .map(|i| i.wrapping_mul(0x9E37_79B9))
+        .collect();
+    let plain: Vec<u8> = to_vec(&(7u8, &values)).unwrap();
+    let blob: Vec<u8> = to_vec(&Samples { id: 7, values }).unwrap();
+
+    let plain_time = time(|| from_bytes::<(u8, Vec<u32>)>(black_box(&plain)).unwrap());
+    let blob_time = time(|| from_bytes::<Samples>(black_box(&blob)).unwrap());
+
+    println!("Vec<u32>: {:?} ({} bytes)", plain_time, plain.len());
+    println!("le_slice: {:?} ({} bytes)", blob_time, blob.len());
+}
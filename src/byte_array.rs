@@ -0,0 +1,93 @@
+//! Fixed-size byte arrays of any length
+
+use core::fmt;
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+use crate::max_size::MaxSize;
+
+/// An array of `N` bytes, written as exactly `N` bytes without a length
+///
+/// This is the same as the encoding of a `[u8; N]`, but serde only
+/// implements that for arrays of up to 32 elements. Keys, hashes and
+/// signatures are often longer.
+///
+/// ```rust
+/// use pinecone::{from_bytes, to_vec, ByteArray};
+///
+/// let signature = ByteArray([0xAB; 64]);
+/// let ser: Vec<u8> = to_vec(&signature).unwrap();
+/// assert_eq!(ser, [0xAB; 64]);
+/// assert_eq!(from_bytes::<ByteArray<64>>(&ser), Ok(signature));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ByteArray<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> From<[u8; N]> for ByteArray<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        ByteArray(bytes)
+    }
+}
+
+impl<const N: usize> From<ByteArray<N>> for [u8; N] {
+    fn from(array: ByteArray<N>) -> Self {
+        array.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for ByteArray<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> MaxSize for ByteArray<N> {
+    const MAX: usize = N;
+}
+
+impl<const N: usize> Serialize for ByteArray<N> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // A tuple is written without a length prefix
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for byte in self.0.iter() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for ByteArray<N> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(N, ByteArrayVisitor)
+    }
+}
+
+struct ByteArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for ByteArrayVisitor<N> {
+    type Value = ByteArray<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {} bytes", N)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<ByteArray<N>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+        }
+        Ok(ByteArray(bytes))
+    }
+}
@@ -0,0 +1,171 @@
+//! Serialize `Vec<Vec<u8>>` with each inner vector copied at once
+//!
+//! serde has no way to tell the serializer that the elements of a sequence
+//! are bytes, so a plain `Vec<Vec<u8>>` is written one byte at a time. With
+//! this helper each inner vector is written as a byte blob, which the
+//! output takes as a single slice. The encoding is the same either way: the
+//! number of vectors, then the length and bytes of each.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Batch {
+//!     #[serde(with = "pinecone::compat::byte_vecs")]
+//!     frames: Vec<Vec<u8>>,
+//! }
+//!
+//! let batch = Batch { frames: vec![vec![1, 2], vec![]] };
+//! let output: Vec<u8> = pinecone::to_vec(&batch).unwrap();
+//! assert_eq!(output, pinecone::to_vec(&batch.frames).unwrap());
+//! ```
+
+use core::fmt;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::prelude::*;
+
+/// Serialize the vectors as a sequence of byte blobs
+pub fn serialize<T, V, S>(value: &T, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    T: AsRef<[V]>,
+    V: AsRef<[u8]>,
+    S: Serializer,
+{
+    let blobs = value.as_ref();
+    let mut seq = serializer.serialize_seq(Some(blobs.len()))?;
+    for blob in blobs {
+        seq.serialize_element(&Blob(blob.as_ref()))?;
+    }
+    seq.end()
+}
+
+/// Deserialize a sequence of byte blobs, each into its own vector
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<Vec<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(BlobsVisitor)
+}
+
+struct Blob<'a>(&'a [u8]);
+
+impl<'a> Serialize for Blob<'a> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct OwnedBlob(Vec<u8>);
+
+impl<'de> Deserialize<'de> for OwnedBlob {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(BlobVisitor)
+    }
+}
+
+struct BlobVisitor;
+
+impl<'de> Visitor<'de> for BlobVisitor {
+    type Value = OwnedBlob;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte blob")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<OwnedBlob, E> {
+        Ok(OwnedBlob(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<OwnedBlob, E> {
+        Ok(OwnedBlob(v))
+    }
+}
+
+struct BlobsVisitor;
+
+impl<'de> Visitor<'de> for BlobsVisitor {
+    type Value = Vec<Vec<u8>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of byte blobs")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Vec<Vec<u8>>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut blobs = Vec::new();
+        while let Some(OwnedBlob(blob)) = seq.next_element()? {
+            blobs.push(blob);
+        }
+        Ok(blobs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::ser::output::VecOutput;
+    use crate::ser::serializer::Serializer as PineconeSerializer;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Batch {
+        #[serde(with = "crate::compat::byte_vecs")]
+        frames: Vec<Vec<u8>>,
+        id: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Naive<'a> {
+        frames: &'a Vec<Vec<u8>>,
+        id: u8,
+    }
+
+    #[test]
+    fn same_as_naive() {
+        let batch = Batch {
+            frames: vec![vec![], vec![0xAB], (0..=255).collect(), vec![0; 1000]],
+            id: 7,
+        };
+        let output: Vec<u8> = to_vec(&batch).unwrap();
+        let naive = Naive {
+            frames: &batch.frames,
+            id: batch.id,
+        };
+        assert_eq!(output, to_vec(&naive).unwrap());
+        assert_eq!(&output[..4], &[0x04, 0x00, 0x01, 0xAB]);
+        assert_eq!(&output[4..6], &[0x80, 0x02]);
+        assert_eq!(output.len(), 1 + 1 + 2 + (2 + 256) + (2 + 1000) + 1);
+        assert_eq!(from_bytes(output.deref()), Ok(batch));
+
+        // Inner vectors can also be borrowed from the caller
+        let frames: [&[u8]; 2] = [b"ab", b"c"];
+        let mut serializer = PineconeSerializer::new(VecOutput::new());
+        serialize(&frames, &mut serializer).unwrap();
+        assert_eq!(
+            serializer.release(),
+            to_vec(&vec![b"ab".to_vec(), b"c".to_vec()])
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            from_bytes::<Batch>(&[0x02, 0x01, 0xAB, 0x03, 0x01]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Serialize `Bytes` and `BytesMut` as length-prefixed byte blobs
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Packet {
+//!     #[serde(with = "pinecone::compat::bytes")]
+//!     payload: bytes::Bytes,
+//! }
+//! ```
+//!
+//! Deserializing always copies the data into a fresh buffer, even when the
+//! input outlives the value, since `Bytes` must own its contents.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use ::bytes::{Bytes, BytesMut};
+use serde::de::{Deserializer, Error, Visitor};
+use serde::ser::Serializer;
+
+use crate::prelude::*;
+
+/// Buffer types that `deserialize` can produce
+pub trait FromBytes: Sized {
+    /// Copy the given bytes into a new buffer
+    fn from_slice(v: &[u8]) -> Self;
+
+    /// Take ownership of an already allocated buffer
+    fn from_vec(v: Vec<u8>) -> Self;
+}
+
+impl FromBytes for Bytes {
+    fn from_slice(v: &[u8]) -> Self {
+        Bytes::copy_from_slice(v)
+    }
+
+    fn from_vec(v: Vec<u8>) -> Self {
+        Bytes::from(v)
+    }
+}
+
+impl FromBytes for BytesMut {
+    fn from_slice(v: &[u8]) -> Self {
+        BytesMut::from(v)
+    }
+
+    fn from_vec(v: Vec<u8>) -> Self {
+        BytesMut::from(&v[..])
+    }
+}
+
+/// Serialize the buffer as a length-prefixed byte blob
+pub fn serialize<T, S>(value: &T, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: Serializer,
+{
+    serializer.serialize_bytes(value.as_ref())
+}
+
+/// Deserialize a length-prefixed byte blob into a new buffer
+pub fn deserialize<'de, T, D>(deserializer: D) -> core::result::Result<T, D::Error>
+where
+    T: FromBytes,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_byte_buf(BytesVisitor(PhantomData))
+}
+
+struct BytesVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for BytesVisitor<T>
+where
+    T: FromBytes,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte buffer")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<T, E>
+    where
+        E: Error,
+    {
+        Ok(T::from_slice(v))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<T, E>
+    where
+        E: Error,
+    {
+        Ok(T::from_vec(v))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Packet {
+        id: u8,
+        #[serde(with = "crate::compat::bytes")]
+        payload: Bytes,
+        #[serde(with = "crate::compat::bytes")]
+        scratch: BytesMut,
+    }
+
+    #[test]
+    fn empty() {
+        let packet = Packet {
+            id: 7,
+            payload: Bytes::new(),
+            scratch: BytesMut::new(),
+        };
+        let output: Vec<u8> = to_vec(&packet).unwrap();
+        assert_eq!(&[0x07, 0x00, 0x00], output.deref());
+        let out: Packet = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, packet);
+    }
+
+    #[test]
+    fn non_empty() {
+        let packet = Packet {
+            id: 7,
+            payload: Bytes::from_static(b"hello"),
+            scratch: BytesMut::from(&b"ab"[..]),
+        };
+        let output: Vec<u8> = to_vec(&packet).unwrap();
+        assert_eq!(
+            &[0x07, 0x05, b'h', b'e', b'l', b'l', b'o', 0x02, b'a', b'b'],
+            output.deref()
+        );
+        let out: Packet = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, packet);
+
+        // Same layout as a plain byte vector
+        let plain: Vec<u8> = to_vec(&b"hello".to_vec()).unwrap();
+        assert_eq!(&output[1..7], plain.deref());
+    }
+}
@@ -0,0 +1,235 @@
+//! Serialize C strings with their terminating NUL
+//!
+//! serde writes a `CString` as its bytes without the NUL, so it has to be
+//! copied into a new allocation to restore the terminator, and a `&CStr`
+//! cannot be borrowed from the input at all. With this helper the NUL is
+//! written too, and Pinecone checks on decode that it is the only one, so
+//! the bytes can be used as a `CStr` in place. A string with a NUL inside,
+//! or without one at the end, fails with `Error::DeserializeBadEncoding`.
+//!
+//! This is for `CString`, and the `borrowed` module is for `&CStr`. The
+//! encoding is not compatible with that of a plain `CString`.
+//!
+//! ```rust
+//! use core::ffi::CStr;
+//! use std::ffi::CString;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Owned {
+//!     #[serde(with = "pinecone::compat::cstr")]
+//!     name: CString,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Borrowed<'a> {
+//!     #[serde(borrow, with = "pinecone::compat::cstr::borrowed")]
+//!     name: &'a CStr,
+//! }
+//!
+//! let owned = Owned { name: CString::new("pine").unwrap() };
+//! let output: Vec<u8> = pinecone::to_vec(&owned).unwrap();
+//! assert_eq!(output, [0x05, b'p', b'i', b'n', b'e', 0x00]);
+//! let borrowed: Borrowed = pinecone::from_bytes(&output).unwrap();
+//! assert_eq!(borrowed.name, owned.name.as_c_str());
+//! ```
+
+use core::ffi::CStr;
+use core::fmt;
+
+use serde::de::{Deserializer, Error, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::prelude::*;
+
+/// Newtype name that tells the Pinecone deserializer to check the
+/// NUL terminator of the bytes
+pub(crate) const CSTR_TOKEN: &str = "$pinecone::CStr";
+
+/// Serialize the bytes of the string, including the NUL
+pub fn serialize<T, S>(value: &T, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    T: AsRef<CStr>,
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(CSTR_TOKEN, &WithNul(value.as_ref()))
+}
+
+/// Deserialize a NUL-terminated string into a `CString`
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<CString, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(CSTR_TOKEN, CStrVisitor)
+}
+
+/// Borrow a `&CStr` from the input
+///
+/// This needs the input to be a slice. Deserializing from a reader fails.
+pub mod borrowed {
+    use super::*;
+
+    /// Serialize the bytes of the string, including the NUL
+    pub fn serialize<T, S>(value: &T, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        T: AsRef<CStr>,
+        S: Serializer,
+    {
+        super::serialize(value, serializer)
+    }
+
+    /// Deserialize a NUL-terminated string borrowed from the input
+    pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<&'de CStr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(CSTR_TOKEN, BorrowedVisitor)
+    }
+}
+
+struct WithNul<'a>(&'a CStr);
+
+impl<'a> Serialize for WithNul<'a> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0.to_bytes_with_nul())
+    }
+}
+
+// Pinecone has checked the bytes already, other formats have not
+fn check<E: Error>(v: &[u8]) -> core::result::Result<&CStr, E> {
+    CStr::from_bytes_with_nul(v).map_err(E::custom)
+}
+
+struct CStrVisitor;
+
+impl<'de> Visitor<'de> for CStrVisitor {
+    type Value = CString;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a NUL-terminated string")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> core::result::Result<CString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<CString, E>
+    where
+        E: Error,
+    {
+        check(v).map(CString::from)
+    }
+}
+
+struct BorrowedVisitor;
+
+impl<'de> Visitor<'de> for BorrowedVisitor {
+    type Value = &'de CStr;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a borrowed NUL-terminated string")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> core::result::Result<&'de CStr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> core::result::Result<&'de CStr, E>
+    where
+        E: Error,
+    {
+        check(v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Owned {
+        #[serde(with = "crate::compat::cstr")]
+        name: CString,
+        id: u8,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Borrowed<'a> {
+        #[serde(borrow, with = "crate::compat::cstr::borrowed")]
+        name: &'a CStr,
+        id: u8,
+    }
+
+    #[test]
+    fn roundtrip() {
+        let owned = Owned {
+            name: CString::new("pinecone").unwrap(),
+            id: 7,
+        };
+        let output: Vec<u8> = to_vec(&owned).unwrap();
+        assert_eq!(output.deref(), b"\x09pinecone\x00\x07");
+        assert_eq!(from_bytes(output.deref()), Ok(owned));
+
+        let borrowed: Borrowed = from_bytes(output.deref()).unwrap();
+        assert_eq!(borrowed.name.to_bytes(), b"pinecone");
+        // Points into the input, NUL included
+        assert_eq!(borrowed.name.as_ptr() as *const u8, output[1..].as_ptr());
+        assert_eq!(to_vec(&borrowed).unwrap(), output);
+
+        let empty = Owned {
+            name: CString::default(),
+            id: 0,
+        };
+        let output: Vec<u8> = to_vec(&empty).unwrap();
+        assert_eq!(output.deref(), &[0x01, 0x00, 0x00]);
+        assert_eq!(from_bytes(output.deref()), Ok(empty));
+    }
+
+    #[test]
+    fn bad_nul() {
+        let cases: &[&[u8]] = &[
+            // A NUL inside
+            b"\x04pi\x00\x00\x07",
+            // No NUL at the end
+            b"\x04pine\x07",
+            // Nothing at all
+            b"\x00\x07",
+        ];
+        for &input in cases {
+            assert_eq!(
+                from_bytes::<Owned>(input),
+                Err(Error::DeserializeBadEncoding)
+            );
+            assert_eq!(
+                from_bytes::<Borrowed>(input),
+                Err(Error::DeserializeBadEncoding)
+            );
+        }
+    }
+
+    #[test]
+    fn plain_cstring() {
+        // serde's own encoding leaves out the NUL, and rejects one inside
+        let name = CString::new("pine").unwrap();
+        let output: Vec<u8> = to_vec(&name).unwrap();
+        assert_eq!(output.deref(), b"\x04pine");
+        assert_eq!(from_bytes(output.deref()), Ok(name));
+        assert!(matches!(
+            from_bytes::<CString>(b"\x04pi\x00e"),
+            Err(Error::SerdeDeCustom(_))
+        ));
+    }
+}
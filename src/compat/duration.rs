@@ -0,0 +1,160 @@
+//! Serialize `Duration` as two varints, seconds and then nanoseconds
+//!
+//! A plain `Duration` is a struct of a `u64` and a `u32`, so it always takes
+//! 12 bytes. With this helper short durations take as little as 2 bytes,
+//! while `Duration::MAX` takes 15. The encoding is not compatible with that
+//! of a plain `Duration`.
+//!
+//! ```rust
+//! use core::time::Duration;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Timeout {
+//!     #[serde(with = "pinecone::compat::duration")]
+//!     after: Duration,
+//! }
+//!
+//! let timeout = Timeout { after: Duration::from_millis(1500) };
+//! let output: Vec<u8> = pinecone::to_vec(&timeout).unwrap();
+//! assert_eq!(output, [0x01, 0x80, 0xCA, 0xB5, 0xEE, 0x01]);
+//! ```
+
+use core::fmt;
+use core::time::Duration;
+
+use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+use crate::varint::{self, VarintU64};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// Bytes in the longest encoding, that of `Duration::MAX`
+const MAX_LEN: usize = VarintU64::MAX_LEN + 5;
+
+/// Serialize the seconds and nanoseconds as varints
+pub fn serialize<S>(value: &Duration, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut secs = VarintU64::new_buf();
+    let secs = VarintU64(value.as_secs()).to_buf(&mut secs);
+    let mut nanos = VarintU64::new_buf();
+    let nanos = VarintU64(value.subsec_nanos().into()).to_buf(&mut nanos);
+
+    // A tuple is written without a length prefix
+    let mut tuple = serializer.serialize_tuple(secs.len() + nanos.len())?;
+    for byte in secs.iter().chain(nanos.iter()) {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+
+/// Deserialize the seconds and nanoseconds from varints
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Pinecone only reads as many elements as the visitor asks for
+    deserializer.deserialize_tuple(MAX_LEN, DurationVisitor)
+}
+
+/// Reads a varint of at most `bits` bits, one byte element at a time
+fn read_varint<'de, A>(seq: &mut A, bits: u32) -> core::result::Result<u64, A::Error>
+where
+    A: SeqAccess<'de>,
+{
+    varint::decode_bits(bits, || {
+        seq.next_element()?
+            .ok_or_else(|| A::Error::custom("unexpected end of varint"))
+    })?
+    .ok_or_else(|| A::Error::custom("varint out of range"))
+}
+
+struct DurationVisitor;
+
+impl<'de> Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("seconds and nanoseconds as varints")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Duration, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let secs = read_varint(&mut seq, 64)?;
+        let nanos = read_varint(&mut seq, 32)? as u32;
+        if nanos >= NANOS_PER_SEC {
+            return Err(A::Error::custom("nanoseconds out of range"));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::{from_bytes, serialized_size, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Timed {
+        #[serde(with = "crate::compat::duration")]
+        elapsed: Duration,
+        id: u8,
+    }
+
+    #[test]
+    fn layout() {
+        let cases: &[(Duration, &[u8])] = &[
+            (Duration::ZERO, &[0x00, 0x00]),
+            (Duration::new(1, 0), &[0x01, 0x00]),
+            (Duration::from_nanos(300), &[0x00, 0xAC, 0x02]),
+            (
+                Duration::new(0, NANOS_PER_SEC - 1),
+                &[0x00, 0xFF, 0x93, 0xEB, 0xDC, 0x03],
+            ),
+            (
+                Duration::MAX,
+                &[
+                    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0xFF, 0x93, 0xEB,
+                    0xDC, 0x03,
+                ],
+            ),
+        ];
+        for &(elapsed, encoded) in cases {
+            let timed = Timed { elapsed, id: 7 };
+            let output: Vec<u8> = to_vec(&timed).unwrap();
+            assert_eq!(&output[..output.len() - 1], encoded);
+            assert_eq!(output.last(), Some(&7));
+            assert_eq!(serialized_size(&timed), Ok(output.len()));
+            let out: Timed = from_bytes(output.deref()).unwrap();
+            assert_eq!(out, timed);
+        }
+    }
+
+    #[test]
+    fn bad_nanos() {
+        // A whole second of nanoseconds, which `Duration::new` would carry
+        assert!(matches!(
+            from_bytes::<Timed>(&[0x00, 0x80, 0x94, 0xEB, 0xDC, 0x03, 0x07]),
+            Err(Error::SerdeDeCustom(_))
+        ));
+        // Too long for a u32
+        assert!(matches!(
+            from_bytes::<Timed>(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x1F, 0x07]),
+            Err(Error::SerdeDeCustom(_))
+        ));
+        assert!(matches!(
+            from_bytes::<Timed>(&[0x00, 0x80]),
+            Err(Error::DeserializeUnexpectedEnd)
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+//! Serialize `Vec<u32>` as one contiguous block of little-endian words
+//!
+//! A plain `Vec<u32>` is decoded one element at a time. With this helper the
+//! words are written as a byte blob instead, prefixed with its length in
+//! bytes, so that decoding is a single pass over a borrowed slice. The values
+//! are converted with `u32::from_le_bytes`, which is a plain copy on
+//! little-endian hosts and a byte swap on big-endian ones. The words are
+//! little-endian regardless of `Options::endianness`. Run
+//! `cargo bench --bench le_slice` to compare it with a plain `Vec<u32>`.
+//!
+//! The length prefix counts bytes rather than elements, so this is not
+//! compatible with the encoding of a plain `Vec<u32>`. It is always a varint,
+//! so this cannot be used with `Options::length_width`.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Samples {
+//!     #[serde(with = "pinecone::compat::le_slice")]
+//!     values: Vec<u32>,
+//! }
+//! ```
+
+use core::fmt;
+use core::mem::{size_of, size_of_val};
+
+use serde::de::{Deserializer, Error, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+use crate::prelude::*;
+use crate::varint::VarintUsize;
+
+/// Serialize the words as a length-prefixed byte blob
+pub fn serialize<T, S>(value: &T, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    T: AsRef<[u32]>,
+    S: Serializer,
+{
+    let words = value.as_ref();
+    // Same layout as `serialize_bytes`, without first copying the words
+    // into a byte buffer
+    let mut blob = serializer.serialize_tuple(2)?;
+    blob.serialize_element(&VarintUsize(size_of_val(words)))?;
+    blob.serialize_element(&Words(words))?;
+    blob.end()
+}
+
+/// Deserialize a length-prefixed byte blob of little-endian words
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<Vec<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_bytes(WordsVisitor)
+}
+
+/// The words without a length prefix
+struct Words<'a>(&'a [u32]);
+
+impl<'a> Serialize for Words<'a> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Bytes rather than `u32`s, which would follow `Options::endianness`
+        let mut tuple = serializer.serialize_tuple(size_of_val(self.0))?;
+        for word in self.0 {
+            for byte in word.to_le_bytes().iter() {
+                tuple.serialize_element(byte)?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+struct WordsVisitor;
+
+impl<'de> Visitor<'de> for WordsVisitor {
+    type Value = Vec<u32>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte blob of little-endian u32 words")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<Vec<u32>, E>
+    where
+        E: Error,
+    {
+        let chunks = v.chunks_exact(size_of::<u32>());
+        if !chunks.remainder().is_empty() {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(chunks
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::{from_bytes, from_bytes_be, to_vec, to_vec_be};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Samples {
+        id: u8,
+        #[serde(with = "crate::compat::le_slice")]
+        values: Vec<u32>,
+    }
+
+    #[test]
+    fn layout() {
+        let samples = Samples {
+            id: 7,
+            values: vec![0x0403_0201, 0xDDCC_BBAA],
+        };
+        let output: Vec<u8> = to_vec(&samples).unwrap();
+        assert_eq!(
+            &[0x07, 0x08, 0x01, 0x02, 0x03, 0x04, 0xAA, 0xBB, 0xCC, 0xDD],
+            output.deref()
+        );
+        let out: Samples = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, samples);
+
+        let empty = Samples {
+            id: 7,
+            values: Vec::new(),
+        };
+        let output: Vec<u8> = to_vec(&empty).unwrap();
+        assert_eq!(&[0x07, 0x00], output.deref());
+        let out: Samples = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, empty);
+    }
+
+    #[test]
+    fn big_endian() {
+        let samples = Samples {
+            id: 7,
+            values: vec![0x0102_0304, 0xDDCC_BBAA],
+        };
+        let output: Vec<u8> = to_vec_be(&samples).unwrap();
+        // The words stay little-endian
+        assert_eq!(
+            &[0x07, 0x08, 0x04, 0x03, 0x02, 0x01, 0xAA, 0xBB, 0xCC, 0xDD],
+            output.deref()
+        );
+        assert_eq!(output, to_vec(&samples).unwrap());
+        let out: Samples = from_bytes_be(output.deref()).unwrap();
+        assert_eq!(out, samples);
+    }
+
+    #[test]
+    fn large() {
+        let samples = Samples {
+            id: 7,
+            values: (0..1_000_000u32)
+                .map(|i| i.wrapping_mul(0x9E37_79B9))
+                .collect(),
+        };
+        let output: Vec<u8> = to_vec(&samples).unwrap();
+        assert_eq!(output.len(), 1 + 4 + 4_000_000);
+        let out: Samples = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, samples);
+    }
+
+    #[test]
+    fn partial_word() {
+        assert!(matches!(
+            from_bytes::<Samples>(&[0x07, 0x03, 0x01, 0x02, 0x03]),
+            Err(Error::SerdeDeCustom(_))
+        ));
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers for using types from other crates with Pinecone
+//!
+//! These are meant to be used with serde's field attributes,
+//! e.g. `#[serde(with = "pinecone::compat::bytes")]`. Some that cannot be
+//! expressed that way come with a macro instead.
+
+#[cfg(feature = "bytes")]
+pub mod bytes;
+
+pub mod byte_vecs;
+pub mod cstr;
+#[cfg(feature = "compact-time")]
+pub mod duration;
+pub mod le_slice;
+pub mod option_bitmap;
+pub mod ordering;
+pub mod range_full;
@@ -0,0 +1,270 @@
+//! Serialize a struct of `Option` fields with a leading presence bitmap
+//!
+//! Normally every `Option` takes a tag byte, even when it is `None`. For
+//! structs where most fields are usually `None`, the `option_bitmap!` macro
+//! instead writes one bit per field, rounded up to whole bytes, followed by
+//! the values of only the fields that are present.
+//!
+//! serde cannot do this for a derived struct, so the macro defines the struct
+//! and implements `Serialize` and `Deserialize` for it. All fields must be
+//! `Option`s, and there can be at most 64 of them. Fields are written in
+//! declaration order, and bit `i % 8` of byte `i / 8` is set if field `i` is
+//! present.
+//!
+//! ```rust
+//! pinecone::option_bitmap! {
+//!     #[derive(Debug, Default, PartialEq)]
+//!     pub struct Settings {
+//!         pub volume: Option<u8>,
+//!         pub name: Option<String>,
+//!         pub offset: Option<i32>,
+//!     }
+//! }
+//!
+//! let settings = Settings {
+//!     volume: Some(7),
+//!     ..Settings::default()
+//! };
+//! let ser = pinecone::to_vec(&settings).unwrap();
+//! assert_eq!(ser.as_slice(), &[0b001, 7]);
+//! assert_eq!(pinecone::from_bytes(&ser), Ok(settings));
+//! ```
+
+use serde::de::{Error, SeqAccess};
+use serde::ser::SerializeTuple;
+use serde::Deserialize;
+
+/// Items used by the code generated by `option_bitmap!`
+#[doc(hidden)]
+pub mod __private {
+    pub use core;
+    pub use serde;
+}
+
+/// Number of bytes in the bitmap of `fields` fields
+pub const fn bitmap_len(fields: usize) -> usize {
+    fields.div_ceil(8)
+}
+
+/// Write the bitmap of `fields` fields
+pub fn write_bitmap<S>(
+    tuple: &mut S,
+    bits: u64,
+    fields: usize,
+) -> core::result::Result<(), S::Error>
+where
+    S: SerializeTuple,
+{
+    let bytes = bits.to_le_bytes();
+    for byte in bytes[..bitmap_len(fields)].iter() {
+        tuple.serialize_element(byte)?;
+    }
+    Ok(())
+}
+
+/// Read the bitmap of `fields` fields, rejecting bits past the last field
+pub fn read_bitmap<'de, A>(seq: &mut A, fields: usize) -> core::result::Result<u64, A::Error>
+where
+    A: SeqAccess<'de>,
+{
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes[..bitmap_len(fields)].iter_mut().enumerate() {
+        *byte = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(i, &"a presence bitmap"))?;
+    }
+    let bits = u64::from_le_bytes(bytes);
+    if fields < 64 && bits >> fields != 0 {
+        return Err(Error::custom("presence bit set for an unknown field"));
+    }
+    Ok(bits)
+}
+
+/// Read the value of field `index`, if its bit is set
+pub fn read_field<'de, A, T>(
+    seq: &mut A,
+    bits: u64,
+    index: usize,
+) -> core::result::Result<Option<T>, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    if bits & (1 << index) == 0 {
+        return Ok(None);
+    }
+    seq.next_element()?
+        .map(Some)
+        .ok_or_else(|| Error::invalid_length(index, &"a present field"))
+}
+
+/// Define a struct of `Option` fields that is serialized with a leading
+/// presence bitmap. See `pinecone::compat::option_bitmap`.
+#[macro_export]
+macro_rules! option_bitmap {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : Option<$ty:ty>),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($field_vis $field: Option<$ty>),*
+        }
+
+        const _: () = {
+            use $crate::compat::option_bitmap::__private::{core, serde};
+            use $crate::compat::option_bitmap::{bitmap_len, read_bitmap, read_field, write_bitmap};
+
+            const FIELDS: usize = [$(stringify!($field)),*].len();
+            assert!(FIELDS <= 64, "option_bitmap! supports at most 64 fields");
+
+            impl serde::Serialize for $name {
+                fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    use serde::ser::SerializeTuple;
+
+                    let present = [$(self.$field.is_some()),*];
+                    let bits = present
+                        .iter()
+                        .enumerate()
+                        .fold(0u64, |bits, (i, p)| bits | (*p as u64) << i);
+                    let len = bitmap_len(FIELDS) + bits.count_ones() as usize;
+
+                    let mut tuple = serializer.serialize_tuple(len)?;
+                    write_bitmap(&mut tuple, bits, FIELDS)?;
+                    $(
+                        if let Some(value) = &self.$field {
+                            tuple.serialize_element(value)?;
+                        }
+                    )*
+                    tuple.end()
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $name {
+                fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    struct BitmapVisitor;
+
+                    impl<'de> serde::de::Visitor<'de> for BitmapVisitor {
+                        type Value = $name;
+
+                        fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                            f.write_str(concat!("struct ", stringify!($name), " with a presence bitmap"))
+                        }
+
+                        fn visit_seq<A>(self, mut seq: A) -> core::result::Result<$name, A::Error>
+                        where
+                            A: serde::de::SeqAccess<'de>,
+                        {
+                            let bits = read_bitmap(&mut seq, FIELDS)?;
+                            let mut index = 0;
+                            $(
+                                let $field = read_field(&mut seq, bits, index)?;
+                                index += 1;
+                            )*
+                            let _ = index;
+                            Ok($name { $($field),* })
+                        }
+                    }
+
+                    // The length is an upper bound, as absent fields are skipped
+                    deserializer.deserialize_tuple(bitmap_len(FIELDS) + FIELDS, BitmapVisitor)
+                }
+            }
+        };
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::de::from_bytes;
+    use crate::error::Error;
+    use crate::ser::to_vec;
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    option_bitmap! {
+        #[derive(Debug, Default, PartialEq)]
+        struct Sparse {
+            a: Option<u8>,
+            b: Option<u16>,
+            c: Option<u32>,
+            d: Option<u64>,
+            e: Option<bool>,
+            f: Option<char>,
+            g: Option<String>,
+            h: Option<Vec<u8>>,
+            i: Option<i32>,
+            j: Option<(u8, u8)>,
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Plain {
+        a: Option<u8>,
+        b: Option<u16>,
+        c: Option<u32>,
+        d: Option<u64>,
+        e: Option<bool>,
+        f: Option<char>,
+        g: Option<String>,
+        h: Option<Vec<u8>>,
+        i: Option<i32>,
+        j: Option<(u8, u8)>,
+    }
+
+    #[test]
+    fn sparse() {
+        let sparse = Sparse {
+            b: Some(0x1234),
+            j: Some((1, 2)),
+            ..Sparse::default()
+        };
+        let output: Vec<u8> = to_vec(&sparse).unwrap();
+        assert_eq!(
+            &[
+                0b0000_0010, // a..h
+                0b0000_0010, // i, j
+                0x34,
+                0x12, // b
+                0x01,
+                0x02, // j
+            ],
+            output.deref()
+        );
+
+        let plain = Plain {
+            b: Some(0x1234),
+            j: Some((1, 2)),
+            ..Plain::default()
+        };
+        assert_eq!(to_vec(&plain).unwrap().len(), 10 + 2 + 2);
+
+        assert_eq!(from_bytes(output.deref()), Ok(sparse));
+        let empty: Vec<u8> = to_vec(&Sparse::default()).unwrap();
+        assert_eq!(&[0x00, 0x00], empty.deref());
+        assert_eq!(from_bytes(empty.deref()), Ok(Sparse::default()));
+    }
+
+    #[test]
+    fn bad_bitmap() {
+        // Bit for an eleventh field
+        assert!(matches!(
+            from_bytes::<Sparse>(&[0x00, 0b0000_0100]),
+            Err(Error::SerdeDeCustom(_))
+        ));
+        assert_eq!(
+            from_bytes::<Sparse>(&[0x01, 0x00]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
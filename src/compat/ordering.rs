@@ -0,0 +1,82 @@
+//! Serialize `core::cmp::Ordering` as a unit enum
+//!
+//! serde has no implementation for `Ordering`, so it is written like an
+//! enum declared as `Less`, `Equal`, `Greater`: a single variant index of
+//! 0, 1 or 2.
+//!
+//! ```rust
+//! use core::cmp::Ordering;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct SortKey {
+//!     field: u8,
+//!     #[serde(with = "pinecone::compat::ordering")]
+//!     direction: Ordering,
+//! }
+//! ```
+
+use core::cmp::Ordering;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Ordering")]
+enum OrderingDef {
+    Less,
+    Equal,
+    Greater,
+}
+
+/// Serialize the ordering as its variant index
+pub fn serialize<S>(value: &Ordering, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    OrderingDef::serialize(value, serializer)
+}
+
+/// Deserialize an ordering from its variant index
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<Ordering, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    OrderingDef::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct SortKey {
+        field: u8,
+        #[serde(with = "crate::compat::ordering")]
+        direction: Ordering,
+    }
+
+    #[test]
+    fn ordering() {
+        let cases = [
+            (Ordering::Less, 0x00),
+            (Ordering::Equal, 0x01),
+            (Ordering::Greater, 0x02),
+        ];
+        for &(direction, index) in cases.iter() {
+            let key = SortKey {
+                field: 0x2A,
+                direction,
+            };
+            let output: Vec<u8> = to_vec(&key).unwrap();
+            assert_eq!(&[0x2A, index], output.deref());
+            let out: SortKey = from_bytes(output.deref()).unwrap();
+            assert_eq!(out, key);
+        }
+
+        assert!(from_bytes::<SortKey>(&[0x2A, 0x03]).is_err());
+    }
+}
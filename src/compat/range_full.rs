@@ -0,0 +1,61 @@
+//! Serialize `core::ops::RangeFull` as a unit
+//!
+//! serde has no implementation for `..`, even though it does for the other
+//! ranges. It carries no data, so it takes zero bytes.
+//!
+//! ```rust
+//! use core::ops::{RangeFull, RangeTo};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Slicing {
+//!     head: RangeTo<u32>,
+//!     #[serde(with = "pinecone::compat::range_full")]
+//!     rest: RangeFull,
+//! }
+//! ```
+
+use core::ops::RangeFull;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serialize the range as a unit
+pub fn serialize<S>(_value: &RangeFull, serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_unit()
+}
+
+/// Deserialize the range from a unit
+pub fn deserialize<'de, D>(deserializer: D) -> core::result::Result<RangeFull, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <()>::deserialize(deserializer).map(|()| ..)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::Serialize;
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Slicing {
+        #[serde(with = "crate::compat::range_full")]
+        all: RangeFull,
+        last: u8,
+    }
+
+    #[test]
+    fn range_full() {
+        let value = Slicing { all: .., last: 7 };
+        let output: Vec<u8> = to_vec(&value).unwrap();
+        assert_eq!(&[0x07], output.deref());
+        assert_eq!(from_bytes(output.deref()), Ok(value));
+    }
+}
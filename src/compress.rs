@@ -0,0 +1,134 @@
+//! Compact encodings for common kinds of data
+
+use core::fmt;
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::de::deserializer::Deserializer as PineconeDeserializer;
+use crate::varint::VarintI64;
+
+use crate::prelude::*;
+
+/// A sequence of integers that is written as differences between neighbours
+///
+/// The first value and then the difference of each value to the previous one
+/// are written as zigzag varints, in a byte blob prefixed with its length.
+/// For slowly changing data, such as sensor readings or timestamps, most
+/// differences fit in a single byte, where a plain `Vec<i64>` takes eight
+/// bytes per value by default. Differences wrap around, so any values can
+/// be stored.
+///
+/// ```rust
+/// use pinecone::{from_bytes, to_vec, DeltaSeq};
+///
+/// let readings = DeltaSeq(vec![1000, 1001, 1003, 1002]);
+/// let ser: Vec<u8> = to_vec(&readings).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x05, 0xD0, 0x0F, 0x02, 0x04, 0x01]);
+/// assert_eq!(from_bytes::<DeltaSeq>(&ser), Ok(readings));
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DeltaSeq(pub Vec<i64>);
+
+impl Serialize for DeltaSeq {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut blob = Vec::with_capacity(self.0.len());
+        let mut buf = VarintI64::new_buf();
+        let mut previous = 0i64;
+        for &value in self.0.iter() {
+            blob.extend_from_slice(VarintI64(value.wrapping_sub(previous)).to_buf(&mut buf));
+            previous = value;
+        }
+        serializer.serialize_bytes(&blob)
+    }
+}
+
+impl<'de> Deserialize<'de> for DeltaSeq {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(DeltaVisitor)
+    }
+}
+
+struct DeltaVisitor;
+
+impl<'de> Visitor<'de> for DeltaVisitor {
+    type Value = DeltaSeq;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte blob of zigzag varint differences")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<DeltaSeq, E>
+    where
+        E: serde::de::Error,
+    {
+        let mut blob = PineconeDeserializer::from_bytes(v);
+        let mut values = Vec::new();
+        let mut previous = 0i64;
+        while !blob.remaining().is_empty() {
+            let delta = blob.try_take_zigzag().map_err(E::custom)?;
+            previous = previous.wrapping_add(delta);
+            values.push(previous);
+        }
+        Ok(DeltaSeq(values))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::{from_bytes, serialized_size, to_vec};
+    use core::ops::Deref;
+
+    #[test]
+    fn ramp() {
+        let ramp: Vec<i64> = (0..1000).map(|i| 1_700_000_000 + i * 3 + i % 2).collect();
+        let delta = DeltaSeq(ramp.clone());
+
+        let output: Vec<u8> = to_vec(&delta).unwrap();
+        assert_eq!(serialized_size(&delta), Ok(output.len()));
+        // Blob length, the first value, and a byte for each difference
+        assert_eq!(output.len(), 2 + 5 + 999);
+        assert!(output.len() * 4 < to_vec(&ramp).unwrap().len());
+        assert_eq!(from_bytes(output.deref()), Ok(delta));
+    }
+
+    #[test]
+    fn edges() {
+        let cases: &[(&[i64], &[u8])] = &[
+            (&[], &[0x00]),
+            (&[-1], &[0x01, 0x01]),
+            (&[5, 5, 5], &[0x03, 0x0A, 0x00, 0x00]),
+        ];
+        for &(values, encoded) in cases {
+            let delta = DeltaSeq(values.to_vec());
+            let output: Vec<u8> = to_vec(&delta).unwrap();
+            assert_eq!(output.deref(), encoded);
+            assert_eq!(from_bytes(output.deref()), Ok(delta));
+        }
+
+        // Differences that overflow an i64 wrap around
+        let delta = DeltaSeq(vec![i64::MIN, i64::MAX, 0, i64::MIN, -1]);
+        let output: Vec<u8> = to_vec(&delta).unwrap();
+        assert_eq!(from_bytes(output.deref()), Ok(delta));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            from_bytes::<DeltaSeq>(&[0x02, 0x02, 0x80]),
+            Err(Error::SerdeDeCustom(_))
+        ));
+        assert_eq!(
+            from_bytes::<DeltaSeq>(&[0x02, 0x02]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
@@ -1,59 +1,334 @@
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
+use core::ffi::CStr;
+use core::marker::PhantomData;
 use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
 
+#[cfg(feature = "use-std")]
+use std::io::Read;
+
+use crate::compat::cstr::CSTR_TOKEN;
+use crate::de::input::{DeInput, Reference, SliceInput};
+use crate::de::limits::Limits;
+use crate::dict::DICT_TOKEN;
 use crate::error::{Error, Result};
-use crate::varint::VarintUsize;
+use crate::options::{Endianness, Options, Width};
+use crate::varint::{self, VarintI64};
+
+use crate::prelude::*;
+
+#[cfg(feature = "use-std")]
+use crate::de::input::ReaderInput;
 
 /// A structure for deserializing a pinecone message
-pub struct Deserializer<'de> {
-    // This string starts with the input data and characters are truncated off
-    // the beginning as data is parsed.
-    pub(crate) input: &'de [u8],
+pub struct Deserializer<'de, I = SliceInput<'de>> {
+    // Source of the input data. Bytes are taken from it as data is parsed.
+    pub(crate) input: I,
+    limits: Limits,
+    options: Options,
+    allocations: usize,
+    str_bytes: usize,
+    dict_bytes: usize,
+    depth: usize,
+    // Literals of `Dict` values, or `None` if they could not be borrowed
+    dict: Vec<Option<&'de [u8]>>,
+    // Set while deserializing the literal of a `Dict` value
+    in_dict: bool,
+    // Borrowed strings are not checked to be UTF-8
+    assume_valid_utf8: bool,
+    _marker: PhantomData<&'de ()>,
 }
 
 impl<'de> Deserializer<'de> {
     /// Obtain a Deserializer from a slice of bytes
     pub fn from_bytes(input: &'de [u8]) -> Self {
-        Deserializer { input }
+        Deserializer::from_input(SliceInput::new(input))
+    }
+
+    /// The bytes that have not been deserialized yet
+    pub fn remaining(&self) -> &'de [u8] {
+        self.input.bytes
+    }
+
+    /// Number of bytes deserialized so far
+    pub fn bytes_consumed(&self) -> usize {
+        self.input.consumed()
+    }
+
+    /// The next byte of input, without consuming it. With the default
+    /// options, for an enum this is its discriminant if it is below 128.
+    pub fn peek_u8(&self) -> Result<u8> {
+        self.remaining()
+            .first()
+            .copied()
+            .ok_or(Error::DeserializeUnexpectedEnd)
     }
 }
 
-impl<'de> Deserializer<'de> {
-    fn try_take_n(&mut self, ct: usize) -> Result<&'de [u8]> {
-        if self.input.len() >= ct {
-            let (a, b) = self.input.split_at(ct);
-            self.input = b;
-            Ok(a)
-        } else {
-            Err(Error::DeserializeUnexpectedEnd)
+#[cfg(feature = "use-std")]
+impl<'de, R: Read> Deserializer<'de, ReaderInput<R>> {
+    /// Obtain a Deserializer that pulls bytes from a reader as needed
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::from_input(ReaderInput::new(reader))
+    }
+}
+
+impl<'de, I> Deserializer<'de, I>
+where
+    I: DeInput<'de>,
+{
+    pub(crate) fn from_input(input: I) -> Self {
+        Deserializer {
+            input,
+            limits: Limits::default(),
+            options: Options::default(),
+            allocations: 0,
+            str_bytes: 0,
+            dict_bytes: 0,
+            depth: 0,
+            dict: Vec::new(),
+            in_dict: false,
+            assume_valid_utf8: false,
+            _marker: PhantomData,
         }
     }
 
-    fn try_take_varint(&mut self) -> Result<usize> {
-        for i in 0..VarintUsize::varint_usize_max() {
-            let val = self.input.get(i).ok_or(Error::DeserializeUnexpectedEnd)?;
-            if (val & 0x80) == 0 {
-                let (a, b) = self.input.split_at(i + 1);
-                self.input = b;
-                let mut out = 0usize;
-                for byte in a.iter().rev() {
-                    out <<= 7;
-                    out |= (byte & 0x7F) as usize;
+    /// Bound the work done while deserializing, for untrusted input
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Bound the nesting of sequences, maps, structs and enums, replacing
+    /// `max_depth` of the current limits
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.limits.max_depth = max_depth;
+        self
+    }
+
+    /// Deserialize using the given encoding options
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Accept any nonzero `Option` tag as `Some`, replacing
+    /// `lenient_option` of the current options
+    pub fn lenient_option(mut self) -> Self {
+        self.options.lenient_option = true;
+        self
+    }
+
+    /// Skip checking that strings borrowed from the input are UTF-8
+    ///
+    /// Validating is a pass over every string, which can be noticeable for
+    /// large payloads. Strings copied out of a reader are still checked.
+    ///
+    /// # Safety
+    ///
+    /// Every string in the input must be valid UTF-8, e.g. because it was
+    /// checked when it was received. Otherwise invalid `&str`s are handed
+    /// out, which is undefined behavior. Never use this on untrusted input.
+    pub unsafe fn assume_valid_utf8(mut self) -> Self {
+        self.assume_valid_utf8 = true;
+        self
+    }
+
+    /// Advance past the next value, which must be a `T`, discarding it
+    ///
+    /// The format does not describe itself, so the type is what says how
+    /// many bytes to skip; `serde::de::IgnoredAny` can't be used here.
+    pub fn skip<T>(&mut self) -> Result<()>
+    where
+        T: de::Deserialize<'de>,
+    {
+        T::deserialize(&mut *self).map(drop)
+    }
+
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>> {
+        self.input.try_take_n(ct)
+    }
+
+    /// Takes a fixed-size integer in the configured byte order,
+    /// returning its little-endian bytes
+    fn try_take_int<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.try_take_n(N)?.as_slice());
+        if self.options.endianness == Endianness::Big {
+            buf.reverse();
+        }
+        Ok(buf)
+    }
+
+    pub(crate) fn try_take_varint(&mut self) -> Result<usize> {
+        // The value fits, as usize is at most 64 bits wide
+        Ok(self.try_take_varint_bits(usize::BITS)? as usize)
+    }
+
+    pub(crate) fn try_take_zigzag(&mut self) -> Result<i64> {
+        Ok(VarintI64::from_zigzag(self.try_take_varint_bits(64)?).0)
+    }
+
+    /// Reads a varint holding an unsigned integer of at most `bits` bits.
+    ///
+    /// Encodings that are too long, or carry bits past the limit, are rejected
+    /// instead of being truncated. This matters when a value written on a
+    /// 64-bit platform is read on a 32-bit one.
+    pub(crate) fn try_take_varint_bits(&mut self, bits: u32) -> Result<u64> {
+        varint::decode_bits(bits, || Ok(self.try_take_n(1)?.as_slice()[0]))?
+            .ok_or(Error::DeserializeBadVarint)
+    }
+
+    /// Takes a value encoded in the given width
+    fn try_take_width(&mut self, width: Width) -> Result<usize> {
+        match width {
+            Width::Varint => self.try_take_varint(),
+            Width::U8 => Ok(self.try_take_n(1)?.as_slice()[0] as usize),
+            Width::U16 => {
+                let bytes = self.try_take_n(2)?;
+                let bytes = bytes.as_slice();
+                Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            }
+            Width::U32 => {
+                let bytes = self.try_take_n(4)?;
+                let bytes = bytes.as_slice();
+                let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                usize::try_from(value).map_err(|_| Error::DeserializeBadEncoding)
+            }
+        }
+    }
+
+    /// Reads the length of a sequence or a map, checking it against the limits
+    pub(crate) fn try_take_len(&mut self) -> Result<usize> {
+        let len = self.try_take_width(self.options.length)?;
+        if len > self.limits.max_len {
+            return Err(Error::DeserializeOverLongSequence);
+        }
+        if self.limits.len_within_input {
+            match self.input.remaining() {
+                Some(remaining) if len > remaining => {
+                    return Err(Error::DeserializeOverLongSequence)
                 }
-                return Ok(out);
+                _ => {}
             }
         }
+        Ok(len)
+    }
 
-        Err(Error::DeserializeBadVarint)
+    /// Runs `f` one nesting level deeper, failing if that exceeds the limit
+    fn nest<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::DeserializeTooDeep);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Takes a length-prefixed C string, including its terminating NUL
+    fn deserialize_cstr<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.count_allocation()?;
+        let sz = self.try_take_width(self.options.length)?;
+        let bytes = self.try_take_n(sz)?;
+        CStr::from_bytes_with_nul(bytes.as_slice()).map_err(|_| Error::DeserializeBadEncoding)?;
+        match bytes {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
+    }
+
+    /// Reads a `Dict` value, either as a literal or as a reference to an
+    /// earlier literal, which is then deserialized again
+    fn deserialize_dict<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tag = self.try_take_varint()?;
+        if tag == 0 {
+            if self.in_dict {
+                // Values nested in a literal are always literals themselves
+                return visitor.visit_newtype_struct(self);
+            }
+            let before = self.input.borrow_remaining();
+            self.in_dict = true;
+            let value = visitor.visit_newtype_struct(&mut *self);
+            self.in_dict = false;
+            let literal = before.map(|before| {
+                let after = self.input.remaining().unwrap_or(0);
+                &before[..before.len() - after]
+            });
+            self.dict.push(literal);
+            return value;
+        }
+
+        if self.in_dict {
+            return Err(Error::DeserializeBadEncoding);
+        }
+        let literal = match self.dict.get(tag - 1) {
+            Some(Some(literal)) => *literal,
+            Some(None) => return Err(Error::WontImplement),
+            None => return Err(Error::DeserializeBadEncoding),
+        };
+        self.dict_bytes = self.dict_bytes.saturating_add(literal.len());
+        if self.dict_bytes > self.limits.max_dict_bytes {
+            return Err(Error::DeserializeLimitExceeded);
+        }
+
+        // Repeats count towards the limits just like the original
+        let mut repeat = Deserializer::from_bytes(literal)
+            .with_limits(self.limits)
+            .with_options(self.options);
+        repeat.allocations = self.allocations;
+        repeat.str_bytes = self.str_bytes;
+        repeat.depth = self.depth;
+        repeat.in_dict = true;
+        let value = visitor.visit_newtype_struct(&mut repeat);
+        self.allocations = repeat.allocations;
+        self.str_bytes = repeat.str_bytes;
+        value
+    }
+
+    /// Counts a value that may need an allocation towards the limit
+    fn count_allocation(&mut self) -> Result<()> {
+        self.allocations += 1;
+        if self.allocations > self.limits.max_allocations {
+            Err(Error::DeserializeLimitExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Counts the length of a string towards the limit, before validating it
+    fn count_str_bytes(&mut self, len: usize) -> Result<()> {
+        self.str_bytes = self.str_bytes.saturating_add(len);
+        if self.str_bytes > self.limits.max_str_bytes {
+            Err(Error::DeserializeLimitExceeded)
+        } else {
+            Ok(())
+        }
     }
 }
 
-struct MultiAccess<'a, 'b: 'a> {
-    deserializer: &'a mut Deserializer<'b>,
+struct MultiAccess<'a, 'b: 'a, I> {
+    deserializer: &'a mut Deserializer<'b, I>,
     len: usize,
 }
 
-impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for MultiAccess<'a, 'b> {
+impl<'a, 'b: 'a, I: DeInput<'b>> MultiAccess<'a, 'b, I> {
+    // The length is untrusted, and used for preallocating, so it is capped
+    // to the bytes left. Zero-sized elements can make this an underestimate.
+    fn size_hint(&self) -> Option<usize> {
+        match self.deserializer.input.remaining() {
+            Some(remaining) => Some(self.len.min(remaining)),
+            None => Some(self.len),
+        }
+    }
+}
+
+impl<'a, 'b: 'a, I: DeInput<'b>> serde::de::SeqAccess<'b> for MultiAccess<'a, 'b, I> {
     type Error = Error;
 
     fn next_element_seed<V: DeserializeSeed<'b>>(&mut self, seed: V) -> Result<Option<V::Value>> {
@@ -69,11 +344,11 @@ impl<'a, 'b: 'a> serde::de::SeqAccess<'b> for MultiAccess<'a, 'b> {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.len)
+        MultiAccess::size_hint(self)
     }
 }
 
-impl<'de, 'a> serde::de::MapAccess<'de> for MultiAccess<'a, 'de> {
+impl<'de, 'a, I: DeInput<'de>> serde::de::MapAccess<'de> for MultiAccess<'a, 'de, I> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -95,11 +370,15 @@ impl<'de, 'a> serde::de::MapAccess<'de> for MultiAccess<'a, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        Ok(DeserializeSeed::deserialize(seed, &mut *self.deserializer)?)
+        DeserializeSeed::deserialize(seed, &mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        MultiAccess::size_hint(self)
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, I: DeInput<'de>> de::Deserializer<'de> for &mut Deserializer<'de, I> {
     type Error = Error;
 
     // Pinecone does not support structures not known at compile time
@@ -110,12 +389,17 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         Err(Error::WontImplement)
     }
 
+    // Must match the serializer
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     // Take a boolean encoded as a u8
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let val = match self.try_take_n(1)?[0] {
+        let val = match self.try_take_n(1)?.as_slice()[0] {
             0 => false,
             1 => true,
             _ => return Err(Error::DeserializeBadBool),
@@ -128,7 +412,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         let mut buf = [0u8; 1];
-        buf[..].copy_from_slice(self.try_take_n(1)?);
+        buf[..].copy_from_slice(self.try_take_n(1)?.as_slice());
         visitor.visit_i8(i8::from_le_bytes(buf))
     }
 
@@ -136,61 +420,76 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 2];
-        buf[..].copy_from_slice(self.try_take_n(2)?);
-        visitor.visit_i16(i16::from_le_bytes(buf))
+        if self.options.varint_signed {
+            return visitor.visit_i16(
+                i16::try_from(self.try_take_zigzag()?).map_err(|_| Error::DeserializeBadVarint)?,
+            );
+        }
+        visitor.visit_i16(i16::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 4];
-        buf[..].copy_from_slice(self.try_take_n(4)?);
-        visitor.visit_i32(i32::from_le_bytes(buf))
+        if self.options.varint_signed {
+            return visitor.visit_i32(
+                i32::try_from(self.try_take_zigzag()?).map_err(|_| Error::DeserializeBadVarint)?,
+            );
+        }
+        visitor.visit_i32(i32::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 8];
-        buf[..].copy_from_slice(self.try_take_n(8)?);
-        visitor.visit_i64(i64::from_le_bytes(buf))
+        if self.options.varint_signed {
+            return visitor.visit_i64(self.try_take_zigzag()?);
+        }
+        visitor.visit_i64(i64::from_le_bytes(self.try_take_int()?))
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(i128::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(self.try_take_n(1)?[0])
+        visitor.visit_u8(self.try_take_n(1)?.as_slice()[0])
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 2];
-        buf[..].copy_from_slice(self.try_take_n(2)?);
-        visitor.visit_u16(u16::from_le_bytes(buf))
+        visitor.visit_u16(u16::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 4];
-        buf[..].copy_from_slice(self.try_take_n(4)?);
-        visitor.visit_u32(u32::from_le_bytes(buf))
+        visitor.visit_u32(u32::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 8];
-        buf[..].copy_from_slice(self.try_take_n(8)?);
-        visitor.visit_u64(u64::from_le_bytes(buf))
+        visitor.visit_u64(u64::from_le_bytes(self.try_take_int()?))
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(u128::from_le_bytes(self.try_take_int()?))
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
@@ -198,7 +497,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         let bytes = self.try_take_n(4)?;
-        visitor.visit_f32(f32::from_le_bytes(bytes.try_into().unwrap()))
+        visitor.visit_f32(f32::from_le_bytes(bytes.as_slice().try_into().unwrap()))
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
@@ -206,17 +505,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         let bytes = self.try_take_n(8)?;
-        visitor.visit_f64(f64::from_le_bytes(bytes.try_into().unwrap()))
+        visitor.visit_f64(f64::from_le_bytes(bytes.as_slice().try_into().unwrap()))
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let mut buf = [0u8; 4];
-        let bytes = self.try_take_n(4)?;
-        buf.copy_from_slice(bytes);
-        let integer = u32::from_le_bytes(buf);
+        // Written like a `u32`, so it follows the configured byte order too.
+        // Surrogates and values past `char::MAX` are rejected.
+        let integer = u32::from_le_bytes(self.try_take_int()?);
         visitor.visit_char(core::char::from_u32(integer).ok_or(Error::DeserializeBadChar)?)
     }
 
@@ -224,11 +522,25 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let sz = self.try_take_varint()?;
-        let bytes: &'de [u8] = self.try_take_n(sz)?;
-        let str_sl = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
-
-        visitor.visit_borrowed_str(str_sl)
+        self.count_allocation()?;
+        let sz = self.try_take_width(self.options.length)?;
+        self.count_str_bytes(sz)?;
+        let assume_valid_utf8 = self.assume_valid_utf8;
+        match self.try_take_n(sz)? {
+            Reference::Borrowed(bytes) if assume_valid_utf8 => {
+                // SAFETY: The caller of `assume_valid_utf8` vouches for the input
+                let str_sl = unsafe { core::str::from_utf8_unchecked(bytes) };
+                visitor.visit_borrowed_str(str_sl)
+            }
+            Reference::Borrowed(bytes) => {
+                let str_sl = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
+                visitor.visit_borrowed_str(str_sl)
+            }
+            Reference::Copied(bytes) => {
+                let str_sl = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadUtf8)?;
+                visitor.visit_str(str_sl)
+            }
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -242,27 +554,37 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        // AJM - in serialize_bytes, we don't write the length first
-        // is this asymmetry intended?
-        let sz = self.try_take_varint()?;
-        let bytes: &'de [u8] = self.try_take_n(sz)?;
-        visitor.visit_borrowed_bytes(bytes)
+        self.count_allocation()?;
+        let sz = self.try_take_width(self.options.length)?;
+        match self.try_take_n(sz)? {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
     }
 
+    // The whole length-checked range is copied into the buffer at once, and
+    // the visitor takes ownership of it instead of copying it again. serde's
+    // `Vec<u8>` asks for a sequence instead, so only types like
+    // `serde_bytes::ByteBuf` get here.
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_bytes(visitor)
+        self.count_allocation()?;
+        let sz = self.try_take_width(self.options.length)?;
+        let bytes = self.try_take_n(sz)?.as_slice().to_vec();
+        visitor.visit_byte_buf(bytes)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.try_take_n(1)?[0] {
+        let tag = self.try_take_n(1)?.as_slice()[0];
+        match tag {
             0 => visitor.visit_none(),
             1 => visitor.visit_some(self),
+            _ if self.options.lenient_option => visitor.visit_some(self),
             _ => Err(Error::DeserializeBadOption),
         }
     }
@@ -283,22 +605,31 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        if name == DICT_TOKEN {
+            return self.nest(|de| de.deserialize_dict(visitor));
+        }
+        if name == CSTR_TOKEN {
+            return self.nest(|de| de.deserialize_cstr(visitor));
+        }
+        self.nest(|de| visitor.visit_newtype_struct(de))
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let len = self.try_take_varint()?;
-
-        visitor.visit_seq(MultiAccess {
-            deserializer: self,
-            len,
+        self.count_allocation()?;
+        let len = self.try_take_len()?;
+
+        self.nest(|de| {
+            visitor.visit_seq(MultiAccess {
+                deserializer: de,
+                len,
+            })
         })
     }
 
@@ -306,9 +637,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_seq(MultiAccess {
-            deserializer: self,
-            len,
+        self.nest(|de| {
+            visitor.visit_seq(MultiAccess {
+                deserializer: de,
+                len,
+            })
         })
     }
 
@@ -328,10 +661,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let len = self.try_take_varint()?;
-        visitor.visit_map(MultiAccess {
-            deserializer: self,
-            len,
+        self.count_allocation()?;
+        let len = self.try_take_len()?;
+        self.nest(|de| {
+            visitor.visit_map(MultiAccess {
+                deserializer: de,
+                len,
+            })
         })
     }
 
@@ -356,7 +692,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(self)
+        self.nest(|de| visitor.visit_enum(de))
     }
 
     // As a binary format, Pinecone does not encode identifiers
@@ -375,7 +711,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> serde::de::VariantAccess<'de> for &'a mut Deserializer<'de> {
+impl<'de, I: DeInput<'de>> serde::de::VariantAccess<'de> for &mut Deserializer<'de, I> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -399,16 +735,14 @@ impl<'de, 'a> serde::de::VariantAccess<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> serde::de::EnumAccess<'de> for &'a mut Deserializer<'de> {
+impl<'de, I: DeInput<'de>> serde::de::EnumAccess<'de> for &mut Deserializer<'de, I> {
     type Error = Error;
     type Variant = Self;
 
     fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
-        let varint = self.try_take_varint()?;
-        if varint > 0xFFFF_FFFF {
-            return Err(Error::DeserializeBadEnum);
-        }
-        let v = DeserializeSeed::deserialize(seed, (varint as u32).into_deserializer())?;
+        let index = self.try_take_width(self.options.enum_discriminant)?;
+        let index = u32::try_from(index).map_err(|_| Error::DeserializeBadEnum)?;
+        let v = DeserializeSeed::deserialize(seed, index.into_deserializer())?;
         Ok((v, self))
     }
 }
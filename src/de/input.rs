@@ -0,0 +1,151 @@
+#[cfg(feature = "use-std")]
+use std::io::{ErrorKind, Read};
+
+use crate::error::{Error, Result};
+
+use crate::prelude::*;
+
+/// Bytes taken from a `DeInput`
+pub enum Reference<'de, 'a> {
+    /// Borrowed from the input itself, living as long as the input does
+    Borrowed(&'de [u8]),
+    /// Copied into a scratch buffer, valid until the next read
+    Copied(&'a [u8]),
+}
+
+impl<'de, 'a> Reference<'de, 'a> {
+    /// The bytes, regardless of where they are stored
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Reference::Borrowed(bytes) => bytes,
+            Reference::Copied(bytes) => bytes,
+        }
+    }
+}
+
+/// Generic deserialization source
+pub trait DeInput<'de> {
+    /// Takes exactly `ct` bytes from the input, failing with
+    /// `DeserializeUnexpectedEnd` if there are not enough bytes left.
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>>;
+
+    /// Number of bytes left in the input, if known
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// The bytes left in the input, if they can be borrowed from it
+    fn borrow_remaining(&self) -> Option<&'de [u8]> {
+        None
+    }
+}
+
+/// Reads from a byte slice that holds the whole message.
+/// Strings and byte slices can be borrowed from it.
+pub struct SliceInput<'de> {
+    pub(crate) bytes: &'de [u8],
+    // Length of the whole input slice
+    len: usize,
+}
+
+impl<'de> SliceInput<'de> {
+    /// Create from the given input slice
+    pub fn new(bytes: &'de [u8]) -> Self {
+        SliceInput {
+            bytes,
+            len: bytes.len(),
+        }
+    }
+}
+
+impl<'de> SliceInput<'de> {
+    /// Number of bytes taken from the input so far
+    pub fn consumed(&self) -> usize {
+        self.len - self.bytes.len()
+    }
+}
+
+impl<'de> DeInput<'de> for SliceInput<'de> {
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>> {
+        if self.bytes.len() >= ct {
+            let (a, b) = self.bytes.split_at(ct);
+            self.bytes = b;
+            Ok(Reference::Borrowed(a))
+        } else {
+            Err(Error::DeserializeUnexpectedEnd)
+        }
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+
+    fn borrow_remaining(&self) -> Option<&'de [u8]> {
+        Some(self.bytes)
+    }
+}
+
+/// A `SliceInput` that remembers how many bytes were missing
+/// when it last ran out of data
+pub(crate) struct PartialInput<'de> {
+    pub(crate) inner: SliceInput<'de>,
+    pub(crate) missing: usize,
+}
+
+impl<'de> DeInput<'de> for PartialInput<'de> {
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>> {
+        self.missing = ct.saturating_sub(self.inner.bytes.len());
+        self.inner.try_take_n(ct)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.inner.remaining()
+    }
+
+    fn borrow_remaining(&self) -> Option<&'de [u8]> {
+        self.inner.borrow_remaining()
+    }
+}
+
+/// Pulls bytes from a reader as they are needed.
+///
+/// Nothing past the end of the deserialized value is read, so any trailing
+/// data is left in the reader. All bytes are copied into a scratch buffer,
+/// which only grows as far as the reader actually provides data.
+#[cfg(feature = "use-std")]
+pub struct ReaderInput<R: Read> {
+    reader: R,
+    scratch: Vec<u8>,
+}
+
+#[cfg(feature = "use-std")]
+impl<R: Read> ReaderInput<R> {
+    /// Create from the given reader
+    pub fn new(reader: R) -> Self {
+        ReaderInput {
+            reader,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "use-std")]
+impl<'de, R: Read> DeInput<'de> for ReaderInput<R> {
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>> {
+        self.scratch.clear();
+        self.reader
+            .by_ref()
+            .take(ct as u64)
+            .read_to_end(&mut self.scratch)
+            .map_err(|error| match error.kind() {
+                ErrorKind::UnexpectedEof => Error::DeserializeUnexpectedEnd,
+                kind => Error::Io(kind),
+            })?;
+
+        if self.scratch.len() == ct {
+            Ok(Reference::Copied(&self.scratch))
+        } else {
+            Err(Error::DeserializeUnexpectedEnd)
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::options::Options;
+
+/// Bounds on the work done while deserializing untrusted input
+///
+/// Every limit except `max_depth` and `max_dict_bytes` defaults to unlimited,
+/// so only the ones that are set need to be given:
+///
+/// ```rust
+/// use pinecone::Limits;
+///
+/// let limits = Limits {
+///     max_allocations: 64,
+///     ..Limits::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Limits {
+    /// Maximum number of sequences, maps, strings and byte buffers decoded.
+    /// Each of these may need an allocation, even if it is empty.
+    pub max_allocations: usize,
+    /// Maximum total length of all strings decoded, in bytes.
+    /// Each string is validated as UTF-8, which takes time in proportion.
+    pub max_str_bytes: usize,
+    /// Maximum number of elements in a sequence, or entries in a map
+    pub max_len: usize,
+    /// Reject sequences and maps that claim more elements than there are
+    /// bytes left in the input, when the size of the input is known.
+    ///
+    /// This assumes each element takes at least one byte, which does not
+    /// hold for types such as `()`, so it must not be used with them.
+    pub len_within_input: bool,
+    /// Maximum nesting of sequences, maps, structs and enums. Deserializing
+    /// recurses for each level, so this bounds the stack usage. Defaults to 128.
+    pub max_depth: usize,
+    /// Maximum total length of the literals decoded again for repeated
+    /// `Dict` values, in bytes. A repeat takes a single byte of input, but
+    /// is decoded from its literal each time. Defaults to 16 MiB.
+    pub max_dict_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_allocations: usize::MAX,
+            max_str_bytes: usize::MAX,
+            max_len: usize::MAX,
+            len_within_input: false,
+            max_depth: 128,
+            max_dict_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Everything `from_bytes_config` needs to deserialize untrusted input
+///
+/// ```rust
+/// use pinecone::{from_bytes_config, DeConfig, Limits};
+///
+/// let config = DeConfig {
+///     limits: Limits {
+///         max_len: 16,
+///         max_depth: 4,
+///         ..Limits::default()
+///     },
+///     strict: true,
+///     ..DeConfig::default()
+/// };
+/// assert_eq!(from_bytes_config(&[0x02, b'o', b'k'], config), Ok("ok"));
+/// assert!(from_bytes_config::<&str>(&[0x02, b'o', b'k', 0x00], config).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DeConfig {
+    /// Bounds on the work done while deserializing
+    pub limits: Limits,
+    /// Encoding options the data was serialized with
+    pub options: Options,
+    /// Reject input with bytes left over after the value, like
+    /// `from_bytes_strict`
+    pub strict: bool,
+}
@@ -1,30 +1,317 @@
-use serde::Deserialize;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Visitor};
+use serde::{Deserialize, Deserializer as _};
 
 pub(crate) mod deserializer;
+pub mod input;
+pub(crate) mod limits;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::options::{Endianness, Options};
+use crate::prelude::*;
 use deserializer::Deserializer;
+use input::{PartialInput, SliceInput};
+use limits::DeConfig;
+
+#[cfg(feature = "use-std")]
+use serde::de::DeserializeOwned;
 
 /// Deserialize a message of type `T` from a byte slice. The unused portion (if any)
 /// of the byte slice is discarded
 pub fn from_bytes<'a, T>(s: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
+{
+    from_bytes_seed(PhantomData, s)
+}
+
+/// Deserialize a message from a byte slice using the given seed, which can
+/// carry state into deserialization. The unused portion (if any) of the byte
+/// slice is discarded
+pub fn from_bytes_seed<'a, S>(seed: S, s: &'a [u8]) -> Result<S::Value>
+where
+    S: DeserializeSeed<'a>,
 {
     let mut deserializer = Deserializer::from_bytes(s);
+    seed.deserialize(&mut deserializer)
+}
+
+/// Deserialize a message of type `T` from a byte slice, using the given
+/// encoding options. The unused portion (if any) of the byte slice is discarded
+pub fn from_bytes_with_options<'a, T>(s: &'a [u8], options: Options) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s).with_options(options);
+    T::deserialize(&mut deserializer)
+}
+
+/// Deserialize a message of type `T` from a byte slice, with the limits,
+/// options and strictness of `config`. See `DeConfig`
+pub fn from_bytes_config<'a, T>(s: &'a [u8], config: DeConfig) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s)
+        .with_limits(config.limits)
+        .with_options(config.options);
     let t = T::deserialize(&mut deserializer)?;
+    let remaining = deserializer.remaining().len();
+    if config.strict && remaining != 0 {
+        return Err(Error::TrailingBytes { remaining });
+    }
     Ok(t)
 }
 
+/// Deserialize a message of type `T` written by `to_vec_be`, with fixed-size
+/// integers in big-endian byte order. The unused portion (if any) of the byte
+/// slice is discarded
+pub fn from_bytes_be<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_bytes_with_options(s, Options::new().endianness(Endianness::Big))
+}
+
+/// Deserialize a message of type `T` from a byte slice, which must contain
+/// nothing else. Any unused bytes result in `Error::TrailingBytes`
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_strict, Error};
+///
+/// assert_eq!(from_bytes_strict(&[0x01]), Ok(true));
+/// assert_eq!(
+///     from_bytes_strict::<bool>(&[0x01, 0x00]),
+///     Err(Error::TrailingBytes { remaining: 1 })
+/// );
+/// ```
+pub fn from_bytes_strict<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let (t, unused) = take_from_bytes(s)?;
+    if unused.is_empty() {
+        Ok(t)
+    } else {
+        Err(Error::TrailingBytes {
+            remaining: unused.len(),
+        })
+    }
+}
+
 /// Deserialize a message of type `T` from a byte slice. The unused portion (if any)
 /// of the byte slice is returned for further usage
 pub fn take_from_bytes<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: Deserialize<'a>,
+{
+    take_from_bytes_seed(PhantomData, s)
+}
+
+/// Deserialize a message of type `T` from the start of `cursor`, and move
+/// `cursor` past it. On error, `cursor` is left unchanged
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::from_mut_slice;
+///
+/// let mut cursor: &[u8] = &[0x01, 0x02, 0x00, 0xFF];
+/// assert_eq!(from_mut_slice(&mut cursor), Ok(true));
+/// assert_eq!(from_mut_slice(&mut cursor), Ok(2u16));
+/// assert_eq!(cursor, &[0xFF]);
+/// ```
+pub fn from_mut_slice<'a, T>(cursor: &mut &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let (t, rest) = take_from_bytes(cursor)?;
+    *cursor = rest;
+    Ok(t)
+}
+
+/// Like `take_from_bytes`, but also returns the bytes the message was
+/// decoded from, as `(value, consumed, remaining)`
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::split_from_bytes;
+///
+/// let (value, consumed, remaining) = split_from_bytes::<u16>(&[0x34, 0x12, 0xFF]).unwrap();
+/// assert_eq!(value, 0x1234);
+/// assert_eq!(consumed, &[0x34, 0x12]);
+/// assert_eq!(remaining, &[0xFF]);
+/// ```
+pub fn split_from_bytes<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8], &'a [u8])>
 where
     T: Deserialize<'a>,
 {
     let mut deserializer = Deserializer::from_bytes(s);
     let t = T::deserialize(&mut deserializer)?;
-    Ok((t, deserializer.input))
+    let (consumed, remaining) = s.split_at(deserializer.bytes_consumed());
+    Ok((t, consumed, remaining))
+}
+
+/// Deserialize a message from a byte slice using the given seed. The unused
+/// portion (if any) of the byte slice is returned for further usage
+pub fn take_from_bytes_seed<'a, S>(seed: S, s: &'a [u8]) -> Result<(S::Value, &'a [u8])>
+where
+    S: DeserializeSeed<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    let t = seed.deserialize(&mut deserializer)?;
+    Ok((t, deserializer.remaining()))
+}
+
+/// Deserialize a string from a byte slice into an existing `String`
+///
+/// The contents of `out` are replaced, reusing its allocation when it is
+/// large enough. This avoids allocating a new `String` for every message
+/// when decoding in a loop. On error, `out` is left unchanged.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::from_bytes_into_string;
+///
+/// let mut out = String::new();
+/// from_bytes_into_string(&[0x03, b'H', b'i', b'!'], &mut out).unwrap();
+/// assert_eq!(out, "Hi!");
+/// ```
+pub fn from_bytes_into_string(s: &[u8], out: &mut String) -> Result<()> {
+    let mut deserializer = Deserializer::from_bytes(s);
+    deserializer.deserialize_str(StringInPlace(out))
+}
+
+/// Deserialize a message of type `T` from a byte slice into an existing value
+///
+/// This uses serde's `deserialize_in_place`, so that types like `Vec` and
+/// `String` reuse their allocations. Structs derived with serde do the same
+/// for their fields when serde_derive's `deserialize_in_place` feature is
+/// enabled, and otherwise replace the whole value. On error, `place` may
+/// have been partially overwritten.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::from_bytes_in_place;
+///
+/// let mut out: Vec<u16> = Vec::with_capacity(16);
+/// from_bytes_in_place(&[0x02, 0x01, 0x00, 0x02, 0x00], &mut out).unwrap();
+/// assert_eq!(out, [1, 2]);
+/// assert_eq!(out.capacity(), 16);
+/// ```
+pub fn from_bytes_in_place<'a, T>(s: &'a [u8], place: &mut T) -> Result<()>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    T::deserialize_in_place(&mut deserializer, place)
+}
+
+/// Replaces the contents of a `String` with the visited string
+struct StringInPlace<'a>(&'a mut String);
+
+impl<'a, 'de> Visitor<'de> for StringInPlace<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> core::result::Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.0.clear();
+        self.0.push_str(v);
+        Ok(())
+    }
+}
+
+/// Deserialize a message of type `T` from a reader, such as a `File` or a `TcpStream`
+///
+/// Bytes are read only as they are needed, so any data following the
+/// message is left in the reader.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::from_reader;
+///
+/// let mut reader: &[u8] = &[0x03, b'H', b'i', b'!', 0xFF];
+/// let out: String = from_reader(&mut reader).unwrap();
+/// assert_eq!(out, "Hi!");
+/// assert_eq!(reader, &[0xFF]);
+/// ```
+#[cfg(feature = "use-std")]
+pub fn from_reader<R, T>(reader: &mut R) -> Result<T>
+where
+    R: std::io::Read,
+    T: DeserializeOwned,
+{
+    let mut deserializer = Deserializer::from_reader(reader);
+    T::deserialize(&mut deserializer)
+}
+
+/// Whether `decode_vec_partial` decoded the whole sequence
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Needed {
+    /// All elements were decoded
+    Complete,
+    /// The input ended before the next element. At least this many more
+    /// bytes are needed to decode it, but it may need more than that.
+    AtLeast(usize),
+}
+
+/// Deserialize a length-prefixed sequence of `T` from a byte slice that may
+/// be truncated, such as data that is still arriving
+///
+/// Returns the elements that could be fully decoded, and whether the input
+/// ended before the rest of them. Any other error fails the whole decode.
+/// Decoding again with more input starts over from the beginning.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{decode_vec_partial, Needed};
+///
+/// let ser = [0x03, 0x01, 0x00, 0x02, 0x00, 0x03];
+/// assert_eq!(decode_vec_partial::<u16>(&ser), Ok((vec![1, 2], Needed::AtLeast(1))));
+/// ```
+pub fn decode_vec_partial<'a, T>(s: &'a [u8]) -> Result<(Vec<T>, Needed)>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_input(PartialInput {
+        inner: SliceInput::new(s),
+        missing: 0,
+    });
+
+    let mut values = Vec::new();
+    let mut len = match deserializer.try_take_len() {
+        Ok(len) => len,
+        Err(Error::DeserializeUnexpectedEnd) => {
+            return Ok((values, Needed::AtLeast(deserializer.input.missing)));
+        }
+        Err(error) => return Err(error),
+    };
+    while len > 0 {
+        match T::deserialize(&mut deserializer) {
+            Ok(value) => values.push(value),
+            Err(Error::DeserializeUnexpectedEnd) => {
+                return Ok((values, Needed::AtLeast(deserializer.input.missing)));
+            }
+            Err(error) => return Err(error),
+        }
+        len -= 1;
+    }
+    Ok((values, Needed::Complete))
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -34,13 +321,14 @@ mod test {
     #![allow(clippy::unreadable_literal)]
 
     use super::*;
-    use crate::ser::to_vec;
+    use crate::de::limits::Limits;
+    use crate::error::Error;
+    use crate::options::Width;
+    use crate::ser::{to_vec, to_vec_be, to_vec_with_options};
     use core::fmt::Write;
     use core::ops::Deref;
     use serde::{Deserialize, Serialize};
 
-    use crate::prelude::*;
-
     #[test]
     fn de_u8() {
         let output: Vec<u8> = to_vec(&0x05u8).unwrap();
@@ -107,6 +395,26 @@ mod test {
         assert_eq!(out, data);
     }
 
+    #[test]
+    fn de_struct_trailing() {
+        let data = BasicU8S {
+            st: 0xABCD,
+            ei: 0xFE,
+            sf: 0x1234_4321_ABCD_DCBA,
+            tt: 0xACAC_ACAC,
+        };
+
+        let mut output: Vec<u8> = to_vec(&data).unwrap();
+        assert_eq!(from_bytes_strict(output.deref()), Ok(data));
+
+        output.push(0x00);
+        assert_eq!(
+            from_bytes_strict::<BasicU8S>(output.deref()),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+        assert!(from_bytes::<BasicU8S>(output.deref()).is_ok());
+    }
+
     #[test]
     fn de_byte_slice() {
         let input: &[u8] = &[1u8, 2, 3, 4, 5, 6, 7, 8];
@@ -158,6 +466,108 @@ mod test {
         assert_eq!(input.deref(), de.deref());
     }
 
+    #[test]
+    fn varint_overflow() {
+        let u64_max = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        let u32_max = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+
+        let mut de = Deserializer::from_bytes(&u64_max);
+        assert_eq!(de.try_take_varint_bits(64), Ok(u64::MAX));
+
+        // As read on a 32-bit target
+        let mut de = Deserializer::from_bytes(&u64_max);
+        assert_eq!(
+            de.try_take_varint_bits(32),
+            Err(Error::DeserializeBadVarint)
+        );
+        let mut de = Deserializer::from_bytes(&u32_max);
+        assert_eq!(de.try_take_varint_bits(32), Ok(u32::MAX as u64));
+        let mut de = Deserializer::from_bytes(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F]);
+        assert_eq!(
+            de.try_take_varint_bits(32),
+            Err(Error::DeserializeBadVarint)
+        );
+
+        // Bits past 64 in the last byte
+        let mut de =
+            Deserializer::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02]);
+        assert_eq!(
+            de.try_take_varint_bits(64),
+            Err(Error::DeserializeBadVarint)
+        );
+
+        // Too many bytes, even if the value would fit
+        let mut de = Deserializer::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]);
+        assert_eq!(
+            de.try_take_varint_bits(32),
+            Err(Error::DeserializeBadVarint)
+        );
+
+        if usize::BITS == 32 {
+            assert_eq!(
+                from_bytes::<String>(&u64_max),
+                Err(Error::DeserializeBadVarint)
+            );
+        }
+    }
+
+    #[test]
+    fn de_str_into_string() {
+        let mut out = String::with_capacity(64);
+        let ptr = out.as_ptr();
+
+        from_bytes_into_string(&to_vec("first").unwrap(), &mut out).unwrap();
+        assert_eq!(out, "first");
+        from_bytes_into_string(&to_vec("second").unwrap(), &mut out).unwrap();
+        assert_eq!(out, "second");
+        assert_eq!(out.capacity(), 64);
+        assert_eq!(out.as_ptr(), ptr);
+
+        // Invalid UTF-8 leaves the previous contents in place
+        assert_eq!(
+            from_bytes_into_string(&[0x02, 0xC3, 0x28], &mut out),
+            Err(Error::DeserializeBadUtf8)
+        );
+        assert_eq!(out, "second");
+    }
+
+    #[test]
+    fn in_place() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Frame {
+            seq: u32,
+            name: String,
+            samples: Vec<u16>,
+        }
+
+        let first = Frame {
+            seq: 1,
+            name: "first frame".to_string(),
+            samples: (0..100).collect(),
+        };
+        let second = Frame {
+            seq: 2,
+            name: "second".to_string(),
+            samples: vec![7, 8, 9],
+        };
+
+        let mut out = Frame {
+            seq: 0,
+            name: String::new(),
+            samples: Vec::new(),
+        };
+        from_bytes_in_place(&to_vec(&first).unwrap(), &mut out).unwrap();
+        assert_eq!(out, first);
+        let (name, samples) = (out.name.as_ptr(), out.samples.as_ptr());
+
+        // The buffers of the first decode are reused by the second
+        from_bytes_in_place(&to_vec(&second).unwrap(), &mut out).unwrap();
+        assert_eq!(out, second);
+        assert_eq!(out.name.as_ptr(), name);
+        assert_eq!(out.samples.as_ptr(), samples);
+        assert_eq!(out.samples.capacity(), 100);
+    }
+
     #[allow(dead_code)]
     #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
     enum BasicEnum {
@@ -189,21 +599,21 @@ mod test {
         let out: BasicEnum = from_bytes(output.deref()).unwrap();
         assert_eq!(out, BasicEnum::Bim);
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bim(u64::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bim(u64::MAX)).unwrap();
         assert_eq!(
             &[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
             output.deref()
         );
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bib(u16::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bib(u16::MAX)).unwrap();
         assert_eq!(&[0x00, 0xFF, 0xFF], output.deref());
         let out: DataEnum = from_bytes(output.deref()).unwrap();
-        assert_eq!(out, DataEnum::Bib(u16::max_value()));
+        assert_eq!(out, DataEnum::Bib(u16::MAX));
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bap(u8::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bap(u8::MAX)).unwrap();
         assert_eq!(&[0x02, 0xFF], output.deref());
         let out: DataEnum = from_bytes(output.deref()).unwrap();
-        assert_eq!(out, DataEnum::Bap(u8::max_value()));
+        assert_eq!(out, DataEnum::Bap(u8::MAX));
 
         let output: Vec<u8> = to_vec(&DataEnum::Kim(EnumStruct {
             eight: 0xF0,
@@ -241,6 +651,210 @@ mod test {
         assert_eq!(out, DataEnum::Sho(0x6969, 0x07));
     }
 
+    /// A unit variant of any index
+    #[derive(Debug, Eq, PartialEq)]
+    struct Indexed(u32);
+
+    impl Serialize for Indexed {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_unit_variant("Indexed", self.0, "Indexed")
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Indexed {
+        fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct IndexedVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for IndexedVisitor {
+                type Value = Indexed;
+
+                fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.write_str("a unit variant")
+                }
+
+                fn visit_enum<A>(self, data: A) -> core::result::Result<Indexed, A::Error>
+                where
+                    A: serde::de::EnumAccess<'de>,
+                {
+                    use serde::de::VariantAccess;
+                    let (index, variant) = data.variant::<u32>()?;
+                    variant.unit_variant()?;
+                    Ok(Indexed(index))
+                }
+            }
+
+            deserializer.deserialize_enum("Indexed", &[], IndexedVisitor)
+        }
+    }
+
+    #[test]
+    fn enums_varint_discriminant() {
+        // The default, growing with the index
+        let cases: &[(u32, &[u8])] = &[
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (299, &[0xAB, 0x02]),
+            (u32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+        ];
+        for &(index, encoded) in cases {
+            let output: Vec<u8> = to_vec(&Indexed(index)).unwrap();
+            assert_eq!(output.deref(), encoded);
+            assert_eq!(from_bytes(output.deref()), Ok(Indexed(index)));
+        }
+        assert_eq!(
+            from_bytes::<Indexed>(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F]),
+            Err(Error::DeserializeBadEnum)
+        );
+    }
+
+    #[test]
+    fn enums_u8_discriminant() {
+        let options = Options::new().enum_discriminant_width(Width::U8);
+
+        let data = DataEnum::Sho(0x6969, 0x07);
+        let output: Vec<u8> = to_vec_with_options(&data, options).unwrap();
+        assert_eq!(&[0x05, 0x69, 0x69, 0x07], output.deref());
+        let out: DataEnum = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, data);
+
+        let output: Vec<u8> = to_vec_with_options(&Indexed(255), options).unwrap();
+        assert_eq!(&[0xFF], output.deref());
+        let out: Indexed = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, Indexed(255));
+        assert_eq!(
+            to_vec_with_options(&Indexed(256), options),
+            Err(Error::SerializeBadEnum)
+        );
+
+        // Lengths can be a single byte too
+        let options = Options::new().length_width(Width::U8);
+        let output: Vec<u8> = to_vec_with_options("abc", options).unwrap();
+        assert_eq!(&[0x03, b'a', b'b', b'c'], output.deref());
+        let out: &str = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, "abc");
+        assert_eq!(
+            to_vec_with_options(&[0u8; 256][..], options),
+            Err(Error::SerializeBadLength)
+        );
+    }
+
+    #[test]
+    fn enums_u16_discriminant() {
+        let options = Options::new().enum_discriminant_width(Width::U16);
+
+        let output: Vec<u8> = to_vec_with_options(&BasicEnum::Bap, options).unwrap();
+        assert_eq!(&[0x02, 0x00], output.deref());
+        let out: BasicEnum = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, BasicEnum::Bap);
+
+        let data = DataEnum::Chi {
+            a: 0x0F,
+            b: 0xC7C7C7C7,
+        };
+        let output: Vec<u8> = to_vec_with_options(&data, options).unwrap();
+        assert_eq!(&[0x04, 0x00, 0x0F, 0xC7, 0xC7, 0xC7, 0xC7], output.deref());
+        let out: DataEnum = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, data);
+
+        // Variant indices must exist, and fit in two bytes
+        assert!(from_bytes_with_options::<BasicEnum>(&[0x03, 0x00], options).is_err());
+        assert_eq!(
+            from_bytes_with_options::<BasicEnum>(&[0x01], options),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            to_vec_with_options(&FarVariant, options),
+            Err(Error::SerializeBadEnum)
+        );
+        assert!(to_vec(&FarVariant).is_ok());
+    }
+
+    #[test]
+    fn lenient_option() {
+        let input: &[u8] = &[0x02, 0x34, 0x12, 0x00, 0xFF, 0x01, 0x00];
+
+        type Options4 = (Option<u16>, Option<u8>, Option<bool>, Option<()>);
+
+        assert_eq!(
+            from_bytes::<Options4>(input),
+            Err(Error::DeserializeBadOption)
+        );
+
+        let options = Options::new().lenient_option(true);
+        let out: Options4 = from_bytes_with_options(input, options).unwrap();
+        assert_eq!(out, (Some(0x1234), None, Some(true), None));
+
+        // The same on the deserializer, e.g. for `0xFF` used as `Some`
+        for tag in [0x02, 0xFF] {
+            let input = [tag, 0x07];
+            assert_eq!(
+                from_bytes::<Option<u8>>(&input),
+                Err(Error::DeserializeBadOption)
+            );
+            let mut de = Deserializer::from_bytes(&input).lenient_option();
+            assert_eq!(Option::<u8>::deserialize(&mut de), Ok(Some(0x07)));
+        }
+        let mut de = Deserializer::from_bytes(&[0x00]).lenient_option();
+        assert_eq!(Option::<u8>::deserialize(&mut de), Ok(None));
+    }
+
+    #[test]
+    fn fixed_lengths() {
+        let options = Options::new().length_width(Width::U32);
+
+        let output: Vec<u8> = to_vec_with_options("abc", options).unwrap();
+        assert_eq!(&[0x03, 0x00, 0x00, 0x00, b'a', b'b', b'c'], output.deref());
+        let out: &str = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out, "abc");
+
+        let mut map = BTreeMap::new();
+        map.insert(1u8, vec![0x0102u16]);
+        let data = (map, serde_bytes::ByteBuf::from(vec![0xAA; 200]));
+        let output: Vec<u8> = to_vec_with_options(&data, options).unwrap();
+        assert_eq!(
+            &[
+                0x01, 0x00, 0x00, 0x00, // map length
+                0x01, // key
+                0x01, 0x00, 0x00, 0x00, // vec length
+                0x02, 0x01, // element
+                0xC8, 0x00, 0x00, 0x00, // bytes length
+            ],
+            &output[..15]
+        );
+        assert_eq!(from_bytes_with_options(output.deref(), options), Ok(data));
+
+        // Lengths must fit in the width
+        let options = Options::new().length_width(Width::U16);
+        let long = vec![0u8; 0x1_0000];
+        assert_eq!(
+            to_vec_with_options(&long, options),
+            Err(Error::SerializeBadLength)
+        );
+        let output: Vec<u8> = to_vec_with_options(&long[1..], options).unwrap();
+        assert_eq!(&[0xFF, 0xFF], &output[..2]);
+        let out: Vec<u8> = from_bytes_with_options(output.deref(), options).unwrap();
+        assert_eq!(out.len(), 0xFFFF);
+    }
+
+    /// A unit variant with an index past `u16::MAX`
+    struct FarVariant;
+
+    impl Serialize for FarVariant {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_unit_variant("FarVariant", 0x1_0000, "Far")
+        }
+    }
+
     #[test]
     fn tuples() {
         let output: Vec<u8> = to_vec(&(1u8, 10u32, "Hello!")).unwrap();
@@ -319,6 +933,95 @@ mod test {
         );
     }
 
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct CowStruct<'a> {
+        #[serde(borrow)]
+        bytes: Cow<'a, [u8]>,
+        #[serde(borrow)]
+        str_s: Cow<'a, str>,
+    }
+
+    #[test]
+    fn bad_char() {
+        // A surrogate, and one past the last code point
+        for &code in &[0xD800u32, 0xDFFF, 0x11_0000, u32::MAX] {
+            let output: Vec<u8> = to_vec(&code).unwrap();
+            assert_eq!(
+                from_bytes::<char>(output.deref()),
+                Err(Error::DeserializeBadChar)
+            );
+        }
+
+        for &c in &['\0', '\u{D7FF}', '\u{E000}', char::MAX] {
+            let output: Vec<u8> = to_vec(&c).unwrap();
+            assert_eq!(from_bytes(output.deref()), Ok(c));
+            assert_eq!(from_bytes_be(to_vec_be(&c).unwrap().deref()), Ok(c));
+        }
+    }
+
+    #[test]
+    fn big_endian() {
+        let output: Vec<u8> = to_vec_be(&0xA5C7u16).unwrap();
+        assert_eq!(&[0xA5, 0xC7], output.deref());
+        assert_eq!(from_bytes_be(output.deref()), Ok(0xA5C7u16));
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Legacy {
+            a: u32,
+            b: i16,
+            c: u64,
+            d: i128,
+            e: Vec<u8>,
+            f: f32,
+        }
+
+        let data = Legacy {
+            a: 0x0102_0304,
+            b: -2,
+            c: 0x0102_0304_0506_0708,
+            d: -1,
+            e: vec![0xAA; 130],
+            f: 1.0,
+        };
+        let output: Vec<u8> = to_vec_be(&data).unwrap();
+        assert_eq!(&[0x01, 0x02, 0x03, 0x04], &output[..4]);
+        assert_eq!(&[0xFF, 0xFE], &output[4..6]);
+        // Lengths are varints, and not affected
+        let e = output.len() - 4 - 130 - 2;
+        assert_eq!(&[0x82, 0x01], &output[e..e + 2]);
+        // Floats stay little-endian
+        assert_eq!(&1.0f32.to_le_bytes(), &output[output.len() - 4..]);
+        assert_eq!(from_bytes_be(output.deref()), Ok(data));
+    }
+
+    #[test]
+    fn cow_struct() {
+        let data = CowStruct {
+            bytes: Cow::Owned(vec![0x01, 0x10, 0x02, 0x20]),
+            str_s: Cow::Owned("hElLo".to_string()),
+        };
+        let output: Vec<u8> = to_vec(&data).unwrap();
+        assert_eq!(
+            &[0x04, 0x01, 0x10, 0x02, 0x20, 0x05, b'h', b'E', b'l', b'L', b'o',],
+            output.deref()
+        );
+
+        let out: CowStruct = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, data);
+        assert!(matches!(out.bytes, Cow::Borrowed(_)));
+        assert!(matches!(out.str_s, Cow::Borrowed(_)));
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn cow_struct_reader() {
+        let output: Vec<u8> = to_vec(&("hElLo", &[0x01u8][..])).unwrap();
+        // Nothing to borrow from
+        let (str_s, bytes): (Cow<str>, Cow<[u8]>) = from_reader(&mut output.deref()).unwrap();
+        assert!(matches!(str_s, Cow::Owned(_)));
+        assert!(matches!(bytes, Cow::Owned(_)));
+    }
+
     #[test]
     fn unit() {
         #![allow(clippy::let_unit_value)]
@@ -357,4 +1060,615 @@ mod test {
         let result: HashMap<u8, u8> = from_bytes(&[3, 1, 2, 3, 4, 5, 6]).unwrap();
         assert_eq!(result, hm);
     }
+
+    // The map from `alloc`, which needs neither `std` nor `hashbrown`
+    #[cfg(not(feature = "use-std"))]
+    #[test]
+    fn btreemap_no_std() {
+        let result: alloc::collections::BTreeMap<u8, u8> =
+            from_bytes(&[3, 5, 6, 1, 2, 3, 4]).unwrap();
+        assert!(result
+            .iter()
+            .eq([(&1, &2), (&3, &4), (&5, &6)].iter().cloned()));
+        assert_eq!(to_vec(&result).unwrap().deref(), &[3, 1, 2, 3, 4, 5, 6]);
+    }
+
+    /// Owned byte buffer that can only be decoded in one piece, noting
+    /// whether the visitor was handed the buffer itself
+    #[derive(Debug, PartialEq)]
+    struct ByteBlob {
+        bytes: Vec<u8>,
+        owned: bool,
+    }
+
+    impl<'de> Deserialize<'de> for ByteBlob {
+        fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct BlobVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for BlobVisitor {
+                type Value = ByteBlob;
+
+                fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.write_str("a byte buffer")
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<ByteBlob, E>
+                where
+                    E: serde::de::Error,
+                {
+                    let mut bytes = Vec::with_capacity(v.len());
+                    bytes.extend_from_slice(v);
+                    Ok(ByteBlob {
+                        bytes,
+                        owned: false,
+                    })
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<ByteBlob, E>
+                where
+                    E: serde::de::Error,
+                {
+                    Ok(ByteBlob {
+                        bytes: v,
+                        owned: true,
+                    })
+                }
+            }
+
+            deserializer.deserialize_byte_buf(BlobVisitor)
+        }
+    }
+
+    #[test]
+    fn byte_buf_single_copy() {
+        let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(&[0x80, 0x80, 0x40], &output[..3]);
+
+        // `BlobVisitor` rejects sequences, so this only succeeds if
+        // the whole buffer is passed to the visitor at once, and the
+        // visitor takes ownership of it instead of copying it again
+        let out: ByteBlob = from_bytes(output.deref()).unwrap();
+        assert_eq!(out.bytes, input);
+        assert!(out.owned);
+
+        // Same wire format whether decoded as a sequence or as one buffer
+        let out: Vec<u8> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, input);
+        let out: serde_bytes::ByteBuf = from_bytes(output.deref()).unwrap();
+        assert_eq!(out.into_vec(), input);
+
+        assert_eq!(
+            from_bytes::<ByteBlob>(&output[..output.len() - 1]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn max_allocations() {
+        let limits = Limits {
+            max_allocations: 100,
+            ..Limits::default()
+        };
+
+        // The outer vector and 99 inner ones fit exactly
+        let input: Vec<Vec<u8>> = vec![Vec::new(); 99];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        let out = Vec::<Vec<u8>>::deserialize(&mut de).unwrap();
+        assert_eq!(out, input);
+
+        let input: Vec<Vec<u8>> = vec![Vec::new(); 100];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(
+            Vec::<Vec<u8>>::deserialize(&mut de),
+            Err(Error::DeserializeLimitExceeded)
+        );
+
+        // Nested collections and strings count as well
+        let input: Vec<Vec<Vec<String>>> = vec![vec![vec![String::new(); 10]; 5]; 2];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(
+            Vec::<Vec<Vec<String>>>::deserialize(&mut de),
+            Err(Error::DeserializeLimitExceeded)
+        );
+
+        // Without limits, everything is accepted
+        let out: Vec<Vec<Vec<String>>> = from_bytes(&output).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn config() {
+        let config = DeConfig {
+            limits: Limits {
+                max_depth: 3,
+                max_len: 8,
+                ..Limits::default()
+            },
+            options: Options::new().length_width(Width::U16),
+            strict: true,
+        };
+        let options = config.options;
+
+        let input = vec![vec![1u8, 2], vec![3]];
+        let output: Vec<u8> = to_vec_with_options(&input, options).unwrap();
+        assert_eq!(from_bytes_config(output.deref(), config), Ok(input));
+
+        let deep = vec![vec![vec![vec![0u8]]]];
+        let output: Vec<u8> = to_vec_with_options(&deep, options).unwrap();
+        assert_eq!(
+            from_bytes_config::<Vec<Vec<Vec<Vec<u8>>>>>(output.deref(), config),
+            Err(Error::DeserializeTooDeep)
+        );
+
+        let long = vec![0u8; 9];
+        let output: Vec<u8> = to_vec_with_options(&long, options).unwrap();
+        assert_eq!(
+            from_bytes_config::<Vec<u8>>(output.deref(), config),
+            Err(Error::DeserializeOverLongSequence)
+        );
+
+        let mut output: Vec<u8> = to_vec_with_options(&long[1..], options).unwrap();
+        output.push(0x00);
+        assert_eq!(
+            from_bytes_config::<Vec<u8>>(output.deref(), config),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+        let lax = DeConfig {
+            strict: false,
+            ..config
+        };
+        assert_eq!(from_bytes_config(output.deref(), lax), Ok(vec![0u8; 8]));
+    }
+
+    #[test]
+    fn max_str_bytes() {
+        let limits = Limits {
+            max_str_bytes: 10_000,
+            ..Limits::default()
+        };
+
+        let input: Vec<String> = vec!["a".repeat(2_500); 4];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(Vec::<String>::deserialize(&mut de), Ok(input));
+
+        // Each string is within the limit, but not all of them together
+        let input: Vec<String> = vec!["a".repeat(2_500); 5];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(
+            Vec::<String>::deserialize(&mut de),
+            Err(Error::DeserializeLimitExceeded)
+        );
+
+        // Byte buffers are not validated, and do not count
+        let input: Vec<Vec<u8>> = vec![vec![0; 2_500]; 5];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(Vec::<Vec<u8>>::deserialize(&mut de), Ok(input));
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    enum Tree {
+        Leaf,
+        Node(Box<Tree>),
+    }
+
+    impl Tree {
+        fn with_depth(depth: usize) -> Tree {
+            (1..depth).fold(Tree::Leaf, |tree, _| Tree::Node(Box::new(tree)))
+        }
+    }
+
+    #[test]
+    fn max_depth() {
+        let output: Vec<u8> = to_vec(&Tree::with_depth(10)).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_max_depth(10);
+        assert_eq!(Tree::deserialize(&mut de), Ok(Tree::with_depth(10)));
+
+        let output: Vec<u8> = to_vec(&Tree::with_depth(11)).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_max_depth(10);
+        assert_eq!(Tree::deserialize(&mut de), Err(Error::DeserializeTooDeep));
+
+        // Would overflow the stack without the default limit
+        let mut pathological = vec![0x01; 1_000_000];
+        pathological.push(0x00);
+        assert_eq!(
+            from_bytes::<Tree>(&pathological),
+            Err(Error::DeserializeTooDeep)
+        );
+    }
+
+    /// Sums the bytes of a sequence instead of collecting them
+    struct SumSeed<'a>(&'a mut u64);
+
+    impl<'de, 'a> DeserializeSeed<'de> for SumSeed<'a> {
+        type Value = usize;
+
+        fn deserialize<D>(self, deserializer: D) -> core::result::Result<usize, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            deserializer.deserialize_seq(self)
+        }
+    }
+
+    impl<'de, 'a> Visitor<'de> for SumSeed<'a> {
+        type Value = usize;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of bytes")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> core::result::Result<usize, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut count = 0;
+            while let Some(byte) = seq.next_element::<u8>()? {
+                *self.0 += byte as u64;
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn seed() {
+        let output: Vec<u8> = to_vec(&vec![1u8, 2, 3, 250]).unwrap();
+
+        let mut sum = 0;
+        assert_eq!(from_bytes_seed(SumSeed(&mut sum), &output), Ok(4));
+        assert_eq!(sum, 256);
+
+        let mut input = output.clone();
+        input.extend_from_slice(&output);
+        let (count, rest) = take_from_bytes_seed(SumSeed(&mut sum), &input).unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(sum, 512);
+        assert_eq!(rest, output.deref());
+    }
+
+    #[test]
+    fn huge_len() {
+        // Claims u64::MAX elements, but holds only two bytes
+        let forged: &[u8] = &[
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x01, 0x02,
+        ];
+
+        fn truncated<'a, T: Deserialize<'a> + core::fmt::Debug>(s: &'a [u8]) {
+            assert_eq!(
+                from_bytes::<T>(s).unwrap_err(),
+                Error::DeserializeUnexpectedEnd
+            );
+        }
+        truncated::<Vec<u8>>(forged);
+        truncated::<Vec<u64>>(forged);
+        truncated::<Vec<String>>(forged);
+        truncated::<Vec<Vec<u8>>>(forged);
+        truncated::<String>(forged);
+        truncated::<&str>(forged);
+        truncated::<&[u8]>(forged);
+        truncated::<Cow<str>>(forged);
+        truncated::<serde_bytes::ByteBuf>(forged);
+        truncated::<BTreeMap<u8, u8>>(forged);
+        truncated::<HashMap<u32, String>>(forged);
+
+        // A million `u16`s in four bytes, the length varint and one more
+        let million: &[u8] = &[0xC0, 0x84, 0x3D, 0x01];
+        truncated::<Vec<u16>>(million);
+
+        // Preallocation is bounded by the input, not the claimed length
+        struct Hint;
+
+        impl<'de> Visitor<'de> for Hint {
+            type Value = Option<usize>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence or map")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(
+                self,
+                seq: A,
+            ) -> core::result::Result<Option<usize>, A::Error> {
+                Ok(seq.size_hint())
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> core::result::Result<Option<usize>, A::Error> {
+                Ok(map.size_hint())
+            }
+        }
+
+        let mut de = Deserializer::from_bytes(forged);
+        assert_eq!(de.deserialize_seq(Hint), Ok(Some(2)));
+        let mut de = Deserializer::from_bytes(forged);
+        assert_eq!(de.deserialize_map(Hint), Ok(Some(2)));
+        let mut de = Deserializer::from_bytes(million);
+        assert_eq!(de.deserialize_seq(Hint), Ok(Some(1)));
+    }
+
+    #[test]
+    fn max_len() {
+        // Claims u32::MAX elements, but holds only two bytes
+        let forged: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x01, 0x02];
+
+        let limits = Limits {
+            len_within_input: true,
+            ..Limits::default()
+        };
+        let mut de = Deserializer::from_bytes(forged).with_limits(limits);
+        assert_eq!(
+            Vec::<u8>::deserialize(&mut de),
+            Err(Error::DeserializeOverLongSequence)
+        );
+
+        let limits = Limits {
+            max_len: 2,
+            ..Limits::default()
+        };
+        let mut de = Deserializer::from_bytes(forged).with_limits(limits);
+        assert_eq!(
+            Vec::<u8>::deserialize(&mut de),
+            Err(Error::DeserializeOverLongSequence)
+        );
+
+        let input: HashMap<u8, u8> = [(1, 2), (3, 4), (5, 6)].iter().cloned().collect();
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(
+            HashMap::<u8, u8>::deserialize(&mut de),
+            Err(Error::DeserializeOverLongSequence)
+        );
+
+        // Lengths within the limits are accepted
+        let input: Vec<u8> = vec![1, 2];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let limits = Limits {
+            max_len: 2,
+            len_within_input: true,
+            ..Limits::default()
+        };
+        let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+        assert_eq!(Vec::<u8>::deserialize(&mut de), Ok(input));
+
+        // Without limits, the forged length only fails once the input runs out
+        assert_eq!(
+            from_bytes::<Vec<u8>>(forged),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+
+    /// Reader handing out at most one byte per call
+    #[cfg(feature = "use-std")]
+    struct Trickle<'a>(&'a [u8]);
+
+    #[cfg(feature = "use-std")]
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(out)) => {
+                    *out = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn reader() {
+        let data = DataEnum::Chi {
+            a: 0x0F,
+            b: 0xC7C7C7C7,
+        };
+        let mut output: Vec<u8> = to_vec(&data).unwrap();
+        let mut strings: Vec<u8> = to_vec(&vec!["a".repeat(200), String::new()]).unwrap();
+        output.append(&mut strings);
+        output.extend_from_slice(&[0xAA, 0xBB]);
+
+        let mut reader = Trickle(&output);
+        let out: DataEnum = from_reader(&mut reader).unwrap();
+        assert_eq!(out, data);
+        let out: Vec<String> = from_reader(&mut reader).unwrap();
+        assert_eq!(out, vec!["a".repeat(200), String::new()]);
+
+        // Trailing data is left in the reader
+        assert_eq!(reader.0, &[0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn consumed() {
+        let mut buf = to_vec(&0x1234u16).unwrap();
+        buf.extend(to_vec("Hi!").unwrap());
+        buf.push(0xAA);
+
+        let mut deserializer = Deserializer::from_bytes(buf.deref());
+        assert_eq!(deserializer.bytes_consumed(), 0);
+
+        let first = u16::deserialize(&mut deserializer).unwrap();
+        assert_eq!(first, 0x1234);
+        assert_eq!(deserializer.bytes_consumed(), 2);
+
+        let second = <&str>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(second, "Hi!");
+        assert_eq!(deserializer.bytes_consumed(), 6);
+        assert_eq!(deserializer.remaining(), &[0xAA]);
+    }
+
+    #[test]
+    fn mut_slice() {
+        let mut buf = to_vec(&0x1234u16).unwrap();
+        buf.extend(to_vec("cursor").unwrap());
+        buf.push(0x80);
+
+        let mut cursor = buf.deref();
+        assert_eq!(from_mut_slice::<u16>(&mut cursor), Ok(0x1234));
+        assert_eq!(cursor.len(), 8);
+        assert_eq!(
+            from_mut_slice::<String>(&mut cursor),
+            Ok("cursor".to_string())
+        );
+        assert_eq!(cursor, &[0x80]);
+
+        // A truncated value leaves the cursor where it was
+        assert_eq!(
+            from_mut_slice::<u16>(&mut cursor),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(cursor, &[0x80]);
+    }
+
+    #[test]
+    fn split() {
+        let mut buf = to_vec(&0xDEAD_BEEFu32).unwrap();
+        buf.extend(to_vec("rest").unwrap());
+
+        let (value, consumed, remaining) = split_from_bytes::<u32>(buf.deref()).unwrap();
+        assert_eq!(value, 0xDEAD_BEEF);
+        assert_eq!(consumed, &[0xEF, 0xBE, 0xAD, 0xDE]);
+        assert_eq!(remaining, &[0x04, b'r', b'e', b's', b't']);
+
+        let (value, consumed, remaining) = split_from_bytes::<&str>(remaining).unwrap();
+        assert_eq!(value, "rest");
+        assert_eq!(consumed.len(), 5);
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn peek() {
+        let buf = to_vec(&DataEnum::Bap(0xFF)).unwrap();
+        let mut deserializer = Deserializer::from_bytes(buf.deref());
+        assert_eq!(deserializer.peek_u8(), Ok(0x02));
+        assert_eq!(deserializer.bytes_consumed(), 0);
+
+        let out = DataEnum::deserialize(&mut deserializer).unwrap();
+        assert_eq!(out, DataEnum::Bap(0xFF));
+        assert_eq!(deserializer.peek_u8(), Err(Error::DeserializeUnexpectedEnd));
+    }
+
+    #[test]
+    fn unchecked_utf8() {
+        let input = ("pinecone", vec!["ünïcödé", ""], 'x');
+        let buf = to_vec(&input).unwrap();
+
+        let mut checked = Deserializer::from_bytes(buf.deref());
+        let checked = <(&str, Vec<&str>, char)>::deserialize(&mut checked).unwrap();
+        // SAFETY: The input was just serialized from valid strings
+        let mut unchecked = unsafe { Deserializer::from_bytes(buf.deref()).assume_valid_utf8() };
+        let unchecked = <(&str, Vec<&str>, char)>::deserialize(&mut unchecked).unwrap();
+        assert_eq!(checked, unchecked);
+        assert_eq!(checked, (input.0, vec!["ünïcödé", ""], 'x'));
+    }
+
+    #[test]
+    fn skip() {
+        let buf = to_vec(&(0xFFFF_FFFFu32, "after", 0u8)).unwrap();
+
+        let mut deserializer = Deserializer::from_bytes(buf.deref());
+        deserializer.skip::<u32>().unwrap();
+        assert_eq!(deserializer.bytes_consumed(), 4);
+        let second = String::deserialize(&mut deserializer).unwrap();
+        assert_eq!(second, "after");
+        assert_eq!(deserializer.bytes_consumed(), 10);
+        assert_eq!(deserializer.remaining(), &[0x00]);
+
+        deserializer.skip::<u8>().unwrap();
+        assert_eq!(
+            deserializer.skip::<u8>(),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn vec_partial() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Row {
+            id: u16,
+            value: u32,
+        }
+
+        let rows: Vec<Row> = (1..=3)
+            .map(|i| Row {
+                id: i,
+                value: 0x100 * i as u32,
+            })
+            .collect();
+        let output: Vec<u8> = to_vec(&rows).unwrap();
+        assert_eq!(output.len(), 1 + 3 * 6);
+        assert_eq!(
+            decode_vec_partial(output.deref()),
+            Ok((rows, Needed::Complete))
+        );
+
+        // Ends in the middle of the value of the third row
+        let (out, needed): (Vec<Row>, Needed) =
+            decode_vec_partial(&output[..1 + 6 + 6 + 3]).unwrap();
+        assert_eq!(
+            out,
+            vec![
+                Row {
+                    id: 1,
+                    value: 0x100
+                },
+                Row {
+                    id: 2,
+                    value: 0x200
+                },
+            ]
+        );
+        assert_eq!(needed, Needed::AtLeast(3));
+
+        // Ends in the middle of the length
+        assert_eq!(
+            decode_vec_partial::<u8>(&[0x80]),
+            Ok((vec![], Needed::AtLeast(1)))
+        );
+        assert_eq!(
+            decode_vec_partial::<u8>(&[]),
+            Ok((vec![], Needed::AtLeast(1)))
+        );
+
+        // Other errors are not recovered from
+        assert_eq!(
+            decode_vec_partial::<bool>(&[0x02, 0x01, 0x02]),
+            Err(Error::DeserializeBadBool)
+        );
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn reader_unexpected_end() {
+        // Ends in the middle of the length varint
+        let mut reader = Trickle(&[0x80]);
+        assert_eq!(
+            from_reader::<_, String>(&mut reader),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+
+        // Ends in the middle of the string
+        let mut reader = Trickle(&[0x80, 0x01, b'a']);
+        assert_eq!(
+            from_reader::<_, String>(&mut reader),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+
+        let mut reader = Trickle(&[0x01, 0x00]);
+        assert_eq!(
+            from_reader::<_, u32>(&mut reader),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
 }
@@ -0,0 +1,197 @@
+//! Deduplication of repeated values
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Newtype name that tells the Pinecone serializer and deserializer
+/// to handle the value as a `Dict`
+pub(crate) const DICT_TOKEN: &str = "$pinecone::Dict";
+
+/// A value that is written only once per message
+///
+/// The first time a value is serialized, it is written as is, and added to
+/// a dictionary. When an identical value is serialized again, only its index
+/// in the dictionary is written. This saves space for data where the same
+/// values, such as enum variants with the same payload, recur many times.
+///
+/// Values are compared by their serialized bytes. A `Dict` nested in another
+/// one is always written as is.
+///
+/// Referring back to earlier values needs the whole message to be in memory,
+/// so deserializing from a reader fails with `Error::WontImplement` on the
+/// first repeated value.
+///
+/// Each repeat is decoded again from its literal, and allocates again if
+/// the value does, so a small message can decode to far more data than its
+/// size. The total length of the literals decoded for repeats is bounded by
+/// `Limits::max_dict_bytes`, 16 MiB by default.
+///
+/// ```rust
+/// use pinecone::{from_bytes, to_vec, Dict};
+///
+/// let values = vec![Dict("hello".to_string()); 3];
+/// let ser: Vec<u8> = to_vec(&values).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x03, 0x00, 0x05, b'h', b'e', b'l', b'l', b'o', 0x01, 0x01]);
+/// assert_eq!(from_bytes::<Vec<Dict<String>>>(&ser), Ok(values));
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Dict<T>(pub T);
+
+impl<T> Serialize for Dict<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(DICT_TOKEN, &self.0)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Dict<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(DICT_TOKEN, DictVisitor(PhantomData))
+    }
+}
+
+struct DictVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for DictVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Dict<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a dictionary value")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> core::result::Result<Dict<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Dict)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    enum Command {
+        Move { x: u32, y: u32 },
+        Say(String),
+        Stop,
+    }
+
+    #[test]
+    fn repeated() {
+        let commands = [
+            Command::Move { x: 1, y: 2 },
+            Command::Say("hello".to_string()),
+            Command::Stop,
+        ];
+        let plain: Vec<Command> = commands.iter().cycle().take(30).cloned().collect();
+        let input: Vec<Dict<Command>> = plain.iter().cloned().map(Dict).collect();
+
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let plain_output: Vec<u8> = to_vec(&plain).unwrap();
+        // Each literal takes an extra tag byte, and each repeat a single byte
+        assert_eq!(output.len(), 1 + (1 + 9) + (1 + 7) + (1 + 1) + 27);
+        assert!(output.len() < plain_output.len() / 2);
+
+        let out: Vec<Dict<Command>> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn nested() {
+        type Nested = (
+            Dict<(Dict<u16>, Dict<u16>)>,
+            Dict<u16>,
+            Dict<(Dict<u16>, Dict<u16>)>,
+        );
+        let input: Nested = (Dict((Dict(7), Dict(7))), Dict(7), Dict((Dict(7), Dict(7))));
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(
+            &[
+                0x00, 0x00, 0x07, 0x00, 0x00, 0x07, 0x00, // Literal, with nested literals
+                0x00, 0x07, 0x00, // Literal, not seen outside a literal before
+                0x01, // Reference to the first literal
+            ],
+            output.deref()
+        );
+        let out: Nested = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn bad_reference() {
+        assert_eq!(
+            from_bytes::<Vec<Dict<u8>>>(&[0x02, 0x00, 0x05, 0x02]),
+            Err(Error::DeserializeBadEncoding)
+        );
+        // No references inside a literal
+        assert_eq!(
+            from_bytes::<Vec<Dict<Dict<u8>>>>(&[0x02, 0x00, 0x00, 0x05, 0x00, 0x01]),
+            Err(Error::DeserializeBadEncoding)
+        );
+    }
+
+    #[test]
+    fn repeat_limit() {
+        use crate::de::deserializer::Deserializer as PineconeDeserializer;
+        use crate::Limits;
+
+        // The literal is the length and the 100 bytes, decoded again 9 times
+        let input = vec![Dict(vec![0xAAu8; 100]); 10];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let decode = |max_dict_bytes| {
+            let limits = Limits {
+                max_dict_bytes,
+                ..Limits::default()
+            };
+            let mut de = PineconeDeserializer::from_bytes(&output).with_limits(limits);
+            Vec::<Dict<Vec<u8>>>::deserialize(&mut de)
+        };
+        assert_eq!(decode(9 * 101), Ok(input.clone()));
+        assert_eq!(decode(9 * 101 - 1), Err(Error::DeserializeLimitExceeded));
+        assert_eq!(from_bytes(output.deref()), Ok(input));
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn reader() {
+        // Literals can be read
+        let input = vec![Dict(1u8), Dict(2u8)];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(
+            crate::from_reader::<_, Vec<Dict<u8>>>(&mut output.deref()),
+            Ok(input)
+        );
+
+        let input = vec![Dict(1u8), Dict(2u8), Dict(1u8)];
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(
+            crate::from_reader::<_, Vec<Dict<u8>>>(&mut output.deref()),
+            Err(Error::WontImplement)
+        );
+    }
+}
@@ -5,14 +5,27 @@ use core::fmt::{Display, Formatter};
 use crate::prelude::*;
 
 /// This is the error type used by Pinecone
+///
+/// Variants may be added in later versions, and `Io` only exists with the
+/// `use-std` feature, so matches on this type need a wildcard arm.
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     /// This is a feature that Pinecone will never implement
     WontImplement,
     /// The serialize buffer is full
     SerializeBufferFull,
+    /// The serialize buffer is too small for the value
+    SerializeBufferTooSmall {
+        /// Size of the serialized value
+        needed: usize,
+    },
     /// The length of a sequence or map must be known
     SerializeLengthUnknown,
+    /// An enum discriminant does not fit the configured width
+    SerializeBadEnum,
+    /// A length does not fit the configured width
+    SerializeBadLength,
     /// Hit the end of buffer, expected more data
     DeserializeUnexpectedEnd,
     /// Found a varint that didn't terminate. Is the usize too big for this platform?
@@ -25,10 +38,30 @@ pub enum Error {
     DeserializeBadUtf8,
     /// Found an Option discriminant that wasn't 0 or 1
     DeserializeBadOption,
-    /// Found an enum discriminant that was > u32::max_value()
+    /// Found an enum discriminant that does not fit in a `u32`
     DeserializeBadEnum,
     /// The original data was not well encoded
     DeserializeBadEncoding,
+    /// Deserializing would exceed one of the configured `Limits`
+    DeserializeLimitExceeded,
+    /// Found a sequence or map length that is longer than allowed
+    DeserializeOverLongSequence,
+    /// Found values nested deeper than allowed
+    DeserializeTooDeep,
+    /// The input had bytes left after the deserialized value
+    TrailingBytes {
+        /// Number of unused bytes
+        remaining: usize,
+    },
+    /// Tagged data did not start with the expected magic bytes
+    MagicMismatch,
+    /// Tagged data was written with an unsupported format version
+    VersionMismatch,
+    /// The data does not match its checksum
+    ChecksumMismatch,
+    /// Reading or writing failed with an IO error
+    #[cfg(feature = "use-std")]
+    Io(std::io::ErrorKind),
     /// Serde Serialization Error
     SerdeSerCustom(String),
     /// Serde Deserialization Error
@@ -37,12 +70,49 @@ pub enum Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Error::WontImplement => {
+                f.write_str("this is a feature that pinecone will never implement")
+            }
+            Error::SerializeBufferFull => f.write_str("serialize buffer full"),
+            Error::SerializeBufferTooSmall { needed } => {
+                write!(f, "serialize buffer too small, {} bytes needed", needed)
+            }
+            Error::SerializeLengthUnknown => {
+                f.write_str("length of a sequence or map must be known")
+            }
+            Error::SerializeBadEnum => {
+                f.write_str("enum discriminant does not fit the configured width")
+            }
+            Error::SerializeBadLength => f.write_str("length does not fit the configured width"),
+            Error::DeserializeUnexpectedEnd => f.write_str("unexpected end of input"),
+            Error::DeserializeBadVarint => f.write_str("invalid varint"),
+            Error::DeserializeBadBool => f.write_str("invalid bool, expected 0 or 1"),
+            Error::DeserializeBadChar => f.write_str("invalid unicode char"),
+            Error::DeserializeBadUtf8 => f.write_str("invalid utf-8"),
+            Error::DeserializeBadOption => f.write_str("invalid option tag, expected 0 or 1"),
+            Error::DeserializeBadEnum => f.write_str("invalid enum discriminant"),
+            Error::DeserializeBadEncoding => f.write_str("data was not well encoded"),
+            Error::DeserializeLimitExceeded => f.write_str("deserialization limit exceeded"),
+            Error::DeserializeOverLongSequence => {
+                f.write_str("sequence or map is longer than allowed")
+            }
+            Error::DeserializeTooDeep => f.write_str("values are nested deeper than allowed"),
+            Error::TrailingBytes { remaining } => {
+                write!(f, "{} unused bytes after the value", remaining)
+            }
+            Error::MagicMismatch => f.write_str("magic bytes do not match"),
+            Error::VersionMismatch => f.write_str("unsupported format version"),
+            Error::ChecksumMismatch => f.write_str("checksum does not match the data"),
+            #[cfg(feature = "use-std")]
+            Error::Io(kind) => write!(f, "io error: {}", std::io::Error::from(*kind)),
+            Error::SerdeSerCustom(msg) => write!(f, "serialize error: {}", msg),
+            Error::SerdeDeCustom(msg) => write!(f, "deserialize error: {}", msg),
+        }
     }
 }
 
 /// This is the Result type used by Pinecone.
-#[must_use]
 pub type Result<T> = ::core::result::Result<T, Error>;
 
 impl serde::ser::Error for Error {
@@ -63,4 +133,51 @@ impl serde::de::Error for Error {
     }
 }
 
+// This is `std::error::Error` with the `use-std` feature. No variant wraps
+// another error, so there is no source.
 impl serde::ser::StdError for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Error::SerializeBufferFull.to_string(),
+            "serialize buffer full"
+        );
+        assert_eq!(
+            Error::SerializeBufferTooSmall { needed: 12 }.to_string(),
+            "serialize buffer too small, 12 bytes needed"
+        );
+        assert_eq!(
+            Error::DeserializeUnexpectedEnd.to_string(),
+            "unexpected end of input"
+        );
+        assert_eq!(
+            Error::TrailingBytes { remaining: 3 }.to_string(),
+            "3 unused bytes after the value"
+        );
+        assert_eq!(
+            Error::SerdeDeCustom("missing field".to_string()).to_string(),
+            "deserialize error: missing field"
+        );
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn std_error() {
+        fn decode(s: &[u8]) -> std::result::Result<u32, Box<dyn std::error::Error>> {
+            Ok(crate::from_bytes(s)?)
+        }
+
+        let error = decode(&[0x01]).unwrap_err();
+        assert_eq!(error.to_string(), "unexpected end of input");
+        assert!(error.source().is_none());
+        assert_eq!(
+            Error::Io(std::io::ErrorKind::UnexpectedEof).to_string(),
+            "io error: unexpected end of file"
+        );
+    }
+}
@@ -0,0 +1,792 @@
+//! Envelopes around serialized messages
+
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::de::deserializer::Deserializer;
+#[cfg(feature = "crc")]
+use crate::de::input::{DeInput, Reference, SliceInput};
+use crate::de::{from_bytes, from_bytes_strict};
+use crate::error::{Error, Result};
+use crate::ser::output::{SerOutput, VecOutput};
+use crate::ser::serializer::Serializer;
+use crate::ser::{serialized_size, to_slice};
+use crate::varint::VarintUsize;
+
+use crate::prelude::*;
+
+/// Identifies data written by `to_vec_tagged`
+pub const TAG_MAGIC: [u8; 4] = *b"PINE";
+
+/// Version of the wire format, written by `to_vec_tagged`
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Serialize a `T` to a `Vec<u8>`, prefixed with `TAG_MAGIC` and `FORMAT_VERSION`
+///
+/// This is meant for long-term storage, where a future reader needs
+/// to recognize the data and the format version it was written with.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_tagged, to_vec_tagged};
+///
+/// let ser: Vec<u8> = to_vec_tagged(&true).unwrap();
+/// assert_eq!(ser.as_slice(), &[b'P', b'I', b'N', b'E', 0x01, 0x01]);
+/// assert_eq!(from_bytes_tagged(&ser), Ok(true));
+/// ```
+pub fn to_vec_tagged<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut header = Vec::new();
+    header.extend_from_slice(&TAG_MAGIC);
+    header.push(FORMAT_VERSION);
+
+    let mut serializer = Serializer::new(VecOutput(header));
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Deserialize a message of type `T` written by `to_vec_tagged`, after
+/// checking that the magic and the format version match
+pub fn from_bytes_tagged<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    if s.len() < TAG_MAGIC.len() + 1 {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (magic, rest) = s.split_at(TAG_MAGIC.len());
+    if magic != TAG_MAGIC {
+        return Err(Error::MagicMismatch);
+    }
+    if rest[0] != FORMAT_VERSION {
+        return Err(Error::VersionMismatch);
+    }
+    from_bytes(&rest[1..])
+}
+
+/// Serialize a `T` to a `Vec<u8>`, prefixed with the length of the
+/// serialized value as a varint
+///
+/// Frames can be written back-to-back into a stream, and split again
+/// with `take_framed`, without knowing the length of each value up front.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{take_framed, to_vec_framed};
+///
+/// let ser: Vec<u8> = to_vec_framed("Hi!").unwrap();
+/// assert_eq!(ser.as_slice(), &[0x04, 0x03, b'H', b'i', b'!']);
+/// assert_eq!(take_framed(&ser), Ok(("Hi!", &[][..])));
+/// ```
+pub fn to_vec_framed<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(VecOutput::new());
+    value.serialize(&mut serializer)?;
+    let body = serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)?;
+
+    let mut buf = VarintUsize::new_buf();
+    let prefix = VarintUsize(body.len()).to_buf(&mut buf);
+    let mut frame = Vec::with_capacity(prefix.len() + body.len());
+    frame.extend_from_slice(prefix);
+    frame.extend(body);
+    Ok(frame)
+}
+
+/// Deserialize the first frame written by `to_vec_framed`. The bytes after
+/// the frame are returned for further usage
+///
+/// The value must use the whole frame.
+pub fn take_framed<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    let len = deserializer.try_take_varint()?;
+    let rest = deserializer.remaining();
+    if len > rest.len() {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (body, rest) = rest.split_at(len);
+    Ok((from_bytes_strict(body)?, rest))
+}
+
+/// Iterate over the frames written back-to-back by `to_vec_framed`
+///
+/// Values are deserialized one frame at a time, until the buffer is used up.
+/// After an error, such as a frame that is cut short, nothing more is
+/// yielded.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{framed_iter, to_vec_framed, Result};
+///
+/// let mut stream = to_vec_framed(&1u8).unwrap();
+/// stream.extend(to_vec_framed(&2u8).unwrap());
+/// let values: Result<Vec<u8>> = framed_iter(&stream).collect();
+/// assert_eq!(values, Ok(vec![1, 2]));
+/// ```
+pub fn framed_iter<'a, T>(buf: &'a [u8]) -> impl Iterator<Item = Result<T>> + 'a
+where
+    T: Deserialize<'a>,
+{
+    let mut rest = Some(buf);
+    core::iter::from_fn(move || {
+        let s = rest.take().filter(|s| !s.is_empty())?;
+        Some(take_framed(s).map(|(value, next)| {
+            rest = Some(next);
+            value
+        }))
+    })
+}
+
+/// Serialize a `T` into `slot` as a frame like `to_vec_framed`, with the
+/// rest of the slot filled with zeros
+///
+/// Every record written this way takes exactly the size of the slot. Fails
+/// with `SerializeBufferFull` if the frame does not fit.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_slice_fixed, to_slice_fixed};
+///
+/// let mut slot = [0xFF; 6];
+/// to_slice_fixed("Hi!", &mut slot).unwrap();
+/// assert_eq!(slot, [0x04, 0x03, b'H', b'i', b'!', 0x00]);
+/// assert_eq!(from_slice_fixed(&slot), Ok("Hi!"));
+/// ```
+pub fn to_slice_fixed<T>(value: &T, slot: &mut [u8]) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    let len = serialized_size(value)?;
+    let mut buf = VarintUsize::new_buf();
+    let prefix = VarintUsize(len).to_buf(&mut buf);
+    if prefix.len() + len > slot.len() {
+        return Err(Error::SerializeBufferFull);
+    }
+
+    let (head, body) = slot.split_at_mut(prefix.len());
+    head.copy_from_slice(prefix);
+    let used = to_slice(value, body)?.len();
+    body[used..].iter_mut().for_each(|byte| *byte = 0);
+    Ok(())
+}
+
+/// Deserialize a record written by `to_slice_fixed`, ignoring the padding
+/// after it
+pub fn from_slice_fixed<'a, T>(slot: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    take_framed(slot).map(|(value, _padding)| value)
+}
+
+/// Serialize a `T` to a `Vec<u8>`, prefixed with a header of the message
+/// type as a big-endian `u16` and the payload length as a big-endian `u32`
+///
+/// Only the header is in network byte order. The payload is regular
+/// pinecone data.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_netenvelope, to_vec_netenvelope};
+///
+/// let ser: Vec<u8> = to_vec_netenvelope(0x0102, &0x1234u16).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x01, 0x02, 0x00, 0x00, 0x00, 0x02, 0x34, 0x12]);
+/// assert_eq!(from_bytes_netenvelope(&ser), Ok((0x0102, 0x1234u16)));
+/// ```
+pub fn to_vec_netenvelope<T>(msg_type: u16, value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut header = Vec::new();
+    header.extend_from_slice(&msg_type.to_be_bytes());
+    header.extend_from_slice(&[0; 4]);
+
+    let mut serializer = Serializer::new(VecOutput(header));
+    value.serialize(&mut serializer)?;
+    let mut envelope = serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)?;
+
+    let len = u32::try_from(envelope.len() - 6).map_err(|_| Error::SerializeBufferFull)?;
+    envelope[2..6].copy_from_slice(&len.to_be_bytes());
+    Ok(envelope)
+}
+
+/// Deserialize a message written by `to_vec_netenvelope`, returning the
+/// message type and the value
+///
+/// The input must hold exactly one envelope, and the value must use the
+/// whole payload. A payload shorter than the header claims is reported as
+/// `DeserializeUnexpectedEnd`, and bytes left over as `TrailingBytes`.
+pub fn from_bytes_netenvelope<'a, T>(s: &'a [u8]) -> Result<(u16, T)>
+where
+    T: Deserialize<'a>,
+{
+    if s.len() < 6 {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (header, payload) = s.split_at(6);
+    let msg_type = u16::from_be_bytes([header[0], header[1]]);
+    let len = u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    if len > payload.len() {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    if len < payload.len() {
+        return Err(Error::TrailingBytes {
+            remaining: payload.len() - len,
+        });
+    }
+    Ok((msg_type, from_bytes_strict(payload)?))
+}
+
+/// Size of the length footer written by `to_vec_footer`
+pub const FOOTER_LEN: usize = 4;
+
+/// Serialize a `T` to a `Vec<u8>`, followed by a footer holding the length
+/// of the whole record as a little-endian `u32`
+///
+/// The length includes the footer itself. When records are appended one after
+/// another, such as in a journal, this allows reading them backwards from the
+/// end using `decode_last`.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{decode_last, to_vec_footer};
+///
+/// let ser: Vec<u8> = to_vec_footer("Hi!").unwrap();
+/// assert_eq!(ser.as_slice(), &[0x03, b'H', b'i', b'!', 0x08, 0x00, 0x00, 0x00]);
+/// assert_eq!(decode_last(&ser), Ok(("Hi!", 0)));
+/// ```
+pub fn to_vec_footer<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(VecOutput::new());
+    value.serialize(&mut serializer)?;
+    let mut record = serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)?;
+
+    let total = u32::try_from(record.len() + FOOTER_LEN).map_err(|_| Error::SerializeBufferFull)?;
+    record.extend_from_slice(&total.to_le_bytes());
+    Ok(record)
+}
+
+/// Deserialize the last record of type `T` written by `to_vec_footer`
+///
+/// Returns the record, and the offset at which it starts. The data before
+/// that offset holds the preceding records, if any.
+pub fn decode_last<'a, T>(s: &'a [u8]) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    if s.len() < FOOTER_LEN {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (rest, footer) = s.split_at(s.len() - FOOTER_LEN);
+    let total = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]) as usize;
+    if total < FOOTER_LEN || total > s.len() {
+        return Err(Error::DeserializeBadEncoding);
+    }
+
+    let start = s.len() - total;
+    Ok((from_bytes_strict(&rest[start..])?, start))
+}
+
+/// Table for computing the CRC32 one byte at a time
+#[cfg(feature = "crc")]
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            // Reversed IEEE 802.3 polynomial
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32 (IEEE), as used by Ethernet, zlib and PNG
+#[cfg(feature = "crc")]
+fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Feeds more data into a running CRC32, without the final inversion
+#[cfg(feature = "crc")]
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// A `SliceInput` that keeps a running CRC32 of the bytes taken from it
+#[cfg(feature = "crc")]
+struct CrcInput<'de> {
+    inner: SliceInput<'de>,
+    crc: u32,
+}
+
+#[cfg(feature = "crc")]
+impl<'de> CrcInput<'de> {
+    /// Hashes whatever was not consumed and returns the final checksum
+    fn finish(self) -> u32 {
+        !crc32_update(self.crc, self.inner.bytes)
+    }
+}
+
+#[cfg(feature = "crc")]
+impl<'de> DeInput<'de> for CrcInput<'de> {
+    fn try_take_n(&mut self, ct: usize) -> Result<Reference<'de, '_>> {
+        let bytes = self.inner.try_take_n(ct)?;
+        self.crc = crc32_update(self.crc, bytes.as_slice());
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.inner.remaining()
+    }
+
+    fn borrow_remaining(&self) -> Option<&'de [u8]> {
+        self.inner.borrow_remaining()
+    }
+}
+
+/// Serialize a `T` to a `Vec<u8>`, followed by the CRC32 (IEEE) of the
+/// serialized bytes as a little-endian `u32`
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_crc32, to_vec_crc32};
+///
+/// let ser: Vec<u8> = to_vec_crc32(&true).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x01, 0x1B, 0xDF, 0x05, 0xA5]);
+/// assert_eq!(from_bytes_crc32(&ser), Ok(true));
+/// ```
+#[cfg(feature = "crc")]
+pub fn to_vec_crc32<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(VecOutput::new());
+    value.serialize(&mut serializer)?;
+    let mut payload = serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)?;
+
+    let checksum = crc32(&payload);
+    payload.extend_from_slice(&checksum.to_le_bytes());
+    Ok(payload)
+}
+
+/// Deserialize a message of type `T` written by `to_vec_crc32`, after
+/// checking that the data matches its checksum
+#[cfg(feature = "crc")]
+pub fn from_bytes_crc32<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    if s.len() < 4 {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (payload, checksum) = s.split_at(s.len() - 4);
+    let checksum = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    if crc32(payload) != checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+    from_bytes(payload)
+}
+
+/// Deserialize a message of type `T` written by `to_vec_crc32`, computing
+/// the checksum while the payload is decoded instead of in a separate pass
+///
+/// A checksum mismatch takes precedence over any decoding error, since
+/// that error is most likely caused by the corruption.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_checked, to_vec_crc32, Error};
+///
+/// let mut ser: Vec<u8> = to_vec_crc32(&(1u8, 2u8)).unwrap();
+/// assert_eq!(from_bytes_checked(&ser), Ok((1u8, 2u8)));
+///
+/// ser[1] = 3;
+/// assert_eq!(from_bytes_checked::<(u8, u8)>(&ser), Err(Error::ChecksumMismatch));
+/// ```
+#[cfg(feature = "crc")]
+pub fn from_bytes_checked<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    if s.len() < 4 {
+        return Err(Error::DeserializeUnexpectedEnd);
+    }
+    let (payload, checksum) = s.split_at(s.len() - 4);
+    let checksum = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+
+    let mut deserializer = Deserializer::from_input(CrcInput {
+        inner: SliceInput::new(payload),
+        crc: !0,
+    });
+    let value = T::deserialize(&mut deserializer);
+    if deserializer.input.finish() != checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::to_vec;
+    use core::ops::Deref;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    struct Archived {
+        name: String,
+        size: u32,
+    }
+
+    #[test]
+    fn tagged() {
+        let data = Archived {
+            name: "log".to_string(),
+            size: 0x1234,
+        };
+        let output: Vec<u8> = to_vec_tagged(&data).unwrap();
+        assert_eq!(&b"PINE\x01"[..], &output[..5]);
+        assert_eq!(&output[5..], to_vec(&data).unwrap().deref());
+
+        let out: Archived = from_bytes_tagged(output.deref()).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn tagged_mismatch() {
+        let mut output: Vec<u8> = to_vec_tagged(&0x1234u16).unwrap();
+
+        assert_eq!(
+            from_bytes_tagged::<u16>(&output[..4]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+
+        output[4] = FORMAT_VERSION + 1;
+        assert_eq!(
+            from_bytes_tagged::<u16>(output.deref()),
+            Err(Error::VersionMismatch)
+        );
+
+        output[0] = b'X';
+        assert_eq!(
+            from_bytes_tagged::<u16>(output.deref()),
+            Err(Error::MagicMismatch)
+        );
+    }
+
+    #[test]
+    fn framed_stream() {
+        let records = [
+            Archived {
+                name: "first".to_string(),
+                size: 1,
+            },
+            Archived {
+                name: String::new(),
+                size: 0x1234_5678,
+            },
+            Archived {
+                name: "third".to_string(),
+                size: 3,
+            },
+        ];
+
+        let mut stream = Vec::new();
+        for record in records.iter() {
+            stream.extend(to_vec_framed(record).unwrap());
+        }
+
+        let mut rest = stream.deref();
+        let mut out = Vec::new();
+        while !rest.is_empty() {
+            let (record, next): (Archived, &[u8]) = take_framed(rest).unwrap();
+            out.push(record);
+            rest = next;
+        }
+        assert_eq!(out, records);
+
+        let out: Vec<Archived> = framed_iter(stream.deref()).collect::<Result<_>>().unwrap();
+        assert_eq!(out, records);
+
+        // A partial frame at the end is reported once
+        let mut iter = framed_iter::<Archived>(&stream[..stream.len() - 1]);
+        assert_eq!(iter.next(), Some(Ok(records[0].clone())));
+        assert_eq!(iter.next(), Some(Ok(records[1].clone())));
+        assert_eq!(iter.next(), Some(Err(Error::DeserializeUnexpectedEnd)));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(framed_iter::<Archived>(&[]).next(), None);
+    }
+
+    #[test]
+    fn framed_edges() {
+        let output: Vec<u8> = to_vec_framed(&()).unwrap();
+        assert_eq!(&[0x00], output.deref());
+        assert_eq!(take_framed(&[0x00, 0xAA]), Ok(((), &[0xAA][..])));
+
+        // The length is longer than the buffer
+        let output: Vec<u8> = to_vec_framed(&0x1234u16).unwrap();
+        assert_eq!(&[0x02, 0x34, 0x12], output.deref());
+        assert_eq!(
+            take_framed::<u16>(&output[..2]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            take_framed::<u16>(&[]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+
+        // The value does not use the whole frame
+        assert_eq!(
+            take_framed::<u8>(output.deref()),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+    }
+
+    #[test]
+    fn fixed_slots() {
+        let data = Archived {
+            name: "uart".to_string(),
+            size: 0x1234,
+        };
+        let body = to_vec(&data).unwrap();
+
+        // Under-full slots are padded, and the padding is ignored
+        let mut slot = [0xAAu8; 32];
+        to_slice_fixed(&data, &mut slot).unwrap();
+        assert_eq!(slot[0] as usize, body.len());
+        assert_eq!(&slot[1..=body.len()], body.deref());
+        assert!(slot[body.len() + 1..].iter().all(|&byte| byte == 0));
+        assert_eq!(from_slice_fixed(&slot), Ok(data.clone()));
+
+        // An exactly full slot has no padding
+        let mut slot = vec![0xAAu8; body.len() + 1];
+        to_slice_fixed(&data, &mut slot).unwrap();
+        assert_eq!(&slot[1..], body.deref());
+        assert_eq!(from_slice_fixed(&slot), Ok(data.clone()));
+
+        let mut slot = vec![0xAAu8; body.len()];
+        assert_eq!(
+            to_slice_fixed(&data, &mut slot),
+            Err(Error::SerializeBufferFull)
+        );
+        assert_eq!(
+            from_slice_fixed::<Archived>(&slot),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn netenvelope() {
+        let data = Archived {
+            name: "gw".to_string(),
+            size: 0x1234,
+        };
+        let output: Vec<u8> = to_vec_netenvelope(0xBEEF, &data).unwrap();
+        let payload = to_vec(&data).unwrap();
+        assert_eq!(&[0xBE, 0xEF, 0x00, 0x00, 0x00, 0x07], &output[..6]);
+        assert_eq!(&output[6..], payload.deref());
+        assert_eq!(from_bytes_netenvelope(output.deref()), Ok((0xBEEF, data)));
+    }
+
+    #[test]
+    fn netenvelope_mismatch() {
+        let mut output: Vec<u8> = to_vec_netenvelope(1, &0x1234u16).unwrap();
+
+        // Truncated header, and truncated payload
+        assert_eq!(
+            from_bytes_netenvelope::<u16>(&output[..5]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            from_bytes_netenvelope::<u16>(&output[..7]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+
+        // A length that does not cover the whole payload
+        output[5] = 0x01;
+        assert_eq!(
+            from_bytes_netenvelope::<u16>(output.deref()),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+
+        // A value that does not use the whole payload
+        output[5] = 0x02;
+        assert_eq!(
+            from_bytes_netenvelope::<u8>(output.deref()),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+    }
+
+    #[test]
+    fn footer_backwards() {
+        let records = [
+            Archived {
+                name: "first".to_string(),
+                size: 1,
+            },
+            Archived {
+                name: String::new(),
+                size: 2,
+            },
+            Archived {
+                name: "third".to_string(),
+                size: 0x1234_5678,
+            },
+        ];
+
+        let mut journal = Vec::new();
+        for record in records.iter() {
+            journal.extend(to_vec_footer(record).unwrap());
+        }
+
+        let mut end = journal.len();
+        for record in records.iter().rev() {
+            let (out, start): (Archived, usize) = decode_last(&journal[..end]).unwrap();
+            assert_eq!(&out, record);
+            end = start;
+        }
+        assert_eq!(end, 0);
+    }
+
+    #[test]
+    fn footer_mismatch() {
+        let mut output: Vec<u8> = to_vec_footer(&0x1234u16).unwrap();
+        assert_eq!(&[0x34, 0x12, 0x06, 0x00, 0x00, 0x00], output.deref());
+
+        assert_eq!(
+            decode_last::<u16>(&output[3..]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            decode_last::<u16>(&output[1..]),
+            Err(Error::DeserializeBadEncoding)
+        );
+
+        // A length covering more bytes than the value uses
+        output[2] = 0x07;
+        let mut longer = vec![0x00];
+        longer.extend(output);
+        assert_eq!(
+            decode_last::<u16>(longer.deref()),
+            Err(Error::TrailingBytes { remaining: 1 })
+        );
+    }
+
+    #[cfg(feature = "crc")]
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[cfg(feature = "crc")]
+    #[test]
+    fn crc32_framed() {
+        let data = Archived {
+            name: "uart".to_string(),
+            size: 0x1234,
+        };
+        let output: Vec<u8> = to_vec_crc32(&data).unwrap();
+        let payload = to_vec(&data).unwrap();
+        assert_eq!(&output[..payload.len()], payload.deref());
+        assert_eq!(from_bytes_crc32(output.deref()), Ok(data));
+
+        // Any single flipped byte is detected, including in the checksum
+        for i in 0..output.len() {
+            let mut corrupted = output.clone();
+            corrupted[i] ^= 0x01;
+            assert_eq!(
+                from_bytes_crc32::<Archived>(corrupted.deref()),
+                Err(Error::ChecksumMismatch)
+            );
+        }
+
+        assert_eq!(
+            from_bytes_crc32::<Archived>(&output[..3]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+
+    #[cfg(feature = "crc")]
+    #[test]
+    fn crc32_one_pass() {
+        let data = Archived {
+            name: "uart".to_string(),
+            size: 0x1234,
+        };
+        let output: Vec<u8> = to_vec_crc32(&data).unwrap();
+        assert_eq!(from_bytes_checked(output.deref()), Ok(data));
+
+        // Corrupting the string length makes decoding fail early, yet the
+        // mismatch is still what gets reported
+        for i in 0..output.len() {
+            let mut corrupted = output.clone();
+            corrupted[i] ^= 0x01;
+            assert_eq!(
+                from_bytes_checked::<Archived>(corrupted.deref()),
+                Err(Error::ChecksumMismatch)
+            );
+        }
+
+        // Unused payload bytes are covered by the checksum as well
+        let mut padded = to_vec(&7u8).unwrap();
+        padded.push(0xAA);
+        padded.extend_from_slice(&crc32(&padded).to_le_bytes());
+        assert_eq!(from_bytes_checked(padded.deref()), Ok(7u8));
+
+        // A valid checksum over data of the wrong shape is a decoding error
+        let short = to_vec_crc32(&1u8).unwrap();
+        assert_eq!(
+            from_bytes_checked::<(u8, u8)>(short.deref()),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            from_bytes_checked::<u8>(&output[..3]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Sequences with an offset table, for reading single elements
+
+use serde::{Deserialize, Serialize};
+
+use crate::de::deserializer::Deserializer;
+use crate::de::from_bytes_strict;
+use crate::error::{Error, Result};
+use crate::ser::output::{SerOutput, VecOutput};
+use crate::ser::serializer::Serializer;
+use crate::varint::VarintUsize;
+
+use crate::prelude::*;
+
+/// Serialize a sequence of `T` to a `Vec<u8>`, with a table of where each
+/// element starts, so that `IndexedSeq` can decode any single element
+/// without going through the ones before it
+///
+/// The element count is followed by one varint offset per element,
+/// counted from the end of the table, and then the elements themselves.
+/// Each element is serialized on its own, so `Dict` values are never
+/// shared between elements.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{to_vec_indexed, IndexedSeq};
+///
+/// let ser: Vec<u8> = to_vec_indexed(&["a", "bc"]).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x02, 0x00, 0x02, 0x01, b'a', 0x02, b'b', b'c']);
+///
+/// let seq = IndexedSeq::new(&ser).unwrap();
+/// assert_eq!(seq.get(1), Ok("bc"));
+/// ```
+pub fn to_vec_indexed<T>(items: &[T]) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut offsets = Vec::with_capacity(items.len());
+    let mut body = Vec::new();
+    for item in items {
+        offsets.push(body.len());
+        let mut serializer = Serializer::new(VecOutput(body));
+        item.serialize(&mut serializer)?;
+        body = serializer
+            .output
+            .release()
+            .map_err(|_| Error::SerializeBufferFull)?;
+    }
+
+    let mut buf = VarintUsize::new_buf();
+    let mut output = Vec::with_capacity(body.len() + items.len() + 1);
+    output.extend_from_slice(VarintUsize(items.len()).to_buf(&mut buf));
+    for offset in offsets {
+        output.extend_from_slice(VarintUsize(offset).to_buf(&mut buf));
+    }
+    output.extend(body);
+    Ok(output)
+}
+
+/// Reader for data written by `to_vec_indexed`
+///
+/// The offset table is read and checked once, after which each element is
+/// found in constant time.
+pub struct IndexedSeq<'a> {
+    offsets: Vec<usize>,
+    body: &'a [u8],
+}
+
+impl<'a> IndexedSeq<'a> {
+    /// Read the offset table of a sequence written by `to_vec_indexed`
+    pub fn new(s: &'a [u8]) -> Result<Self> {
+        let mut deserializer = Deserializer::from_bytes(s);
+        let len = deserializer.try_take_varint()?;
+        // Each offset takes at least a byte
+        if len > deserializer.remaining().len() {
+            return Err(Error::DeserializeUnexpectedEnd);
+        }
+
+        let mut offsets = Vec::with_capacity(len);
+        for _ in 0..len {
+            offsets.push(deserializer.try_take_varint()?);
+        }
+        let body = deserializer.remaining();
+
+        let ordered = offsets.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !ordered || offsets.first().is_some_and(|&first| first != 0) {
+            return Err(Error::DeserializeBadEncoding);
+        }
+        if offsets.last().is_some_and(|&last| last > body.len()) {
+            return Err(Error::DeserializeUnexpectedEnd);
+        }
+        Ok(IndexedSeq { offsets, body })
+    }
+
+    /// Number of elements in the sequence
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the sequence has no elements
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Deserialize the element at `index`. The element must use all of its
+    /// bytes. An index past the end fails with `DeserializeUnexpectedEnd`.
+    pub fn get<T>(&self, index: usize) -> Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        let start = *self
+            .offsets
+            .get(index)
+            .ok_or(Error::DeserializeUnexpectedEnd)?;
+        let end = match self.offsets.get(index + 1) {
+            Some(&end) => end,
+            None => self.body.len(),
+        };
+        from_bytes_strict(&self.body[start..end])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::to_vec;
+    use core::ops::Deref;
+
+    #[test]
+    fn random_access() {
+        let words = ["pine", "", "cone", "needles and resin", "x"];
+        let output: Vec<u8> = to_vec_indexed(&words).unwrap();
+        assert_eq!(&[0x05, 0x00, 0x05, 0x06, 0x0B, 0x1D], &output[..6]);
+        assert_eq!(&output[6..], to_vec(&words).unwrap().deref());
+
+        let seq = IndexedSeq::new(output.deref()).unwrap();
+        assert_eq!(seq.len(), 5);
+        assert_eq!(seq.get(3), Ok("needles and resin"));
+        for (i, word) in words.iter().enumerate().rev() {
+            assert_eq!(seq.get::<&str>(i), Ok(*word));
+        }
+        assert_eq!(seq.get::<&str>(5), Err(Error::DeserializeUnexpectedEnd));
+
+        let empty = to_vec_indexed::<u8>(&[]).unwrap();
+        assert_eq!(empty.deref(), &[0x00]);
+        assert!(IndexedSeq::new(empty.deref()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn bad_table() {
+        // Offsets going backwards, and an offset past the end of the data
+        assert_eq!(
+            IndexedSeq::new(&[0x03, 0x00, 0x02, 0x01, 0x00, 0x00]).err(),
+            Some(Error::DeserializeBadEncoding)
+        );
+        assert_eq!(
+            IndexedSeq::new(&[0x02, 0x00, 0x05, 0x01, 0x00]).err(),
+            Some(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            IndexedSeq::new(&[0xFF, 0xFF, 0x03, 0x00]).err(),
+            Some(Error::DeserializeUnexpectedEnd)
+        );
+
+        // An element that does not fill its slot
+        let seq = IndexedSeq::new(&[0x01, 0x00, 0x07, 0x08]).unwrap();
+        assert_eq!(seq.get::<u8>(0), Err(Error::TrailingBytes { remaining: 1 }));
+    }
+}
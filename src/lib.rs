@@ -32,6 +32,10 @@
 //! to_slice(&original, &mut buffer).expect("Serialization failed");
 //! assert_eq!(from_bytes(&buffer), Ok(original));
 //! ```
+//!
+//! A `Vec<u8>` is decoded one byte at a time, as serde treats it like any
+//! other sequence. Use `serde_bytes` for large byte buffers: it has the same
+//! encoding, and is decoded in a single copy.
 
 #![cfg_attr(not(feature = "use-std"), no_std)]
 // #![deny(missing_docs)]
@@ -46,23 +50,94 @@ extern crate alloc;
 #[cfg(not(feature = "use-std"))]
 mod prelude {
     pub use alloc::format;
-    pub use alloc::{string::String, vec::Vec};
+    pub use alloc::vec;
+    pub use alloc::{
+        borrow::Cow,
+        boxed::Box,
+        collections::{BTreeMap, BinaryHeap},
+        ffi::CString,
+        rc::Rc,
+        string::{String, ToString},
+        sync::Arc,
+        vec::Vec,
+    };
     #[cfg(test)]
     pub use hashbrown::HashMap;
 }
 
 #[cfg(feature = "use-std")]
 mod prelude {
+    pub use std::borrow::Cow;
     #[cfg(test)]
     pub use std::collections::HashMap;
+    pub use std::collections::{BTreeMap, BinaryHeap};
+    pub use std::ffi::CString;
+    pub use std::rc::Rc;
+    pub use std::sync::Arc;
 }
 
+mod byte_array;
+pub mod compat;
+mod compress;
 mod de;
+mod dict;
 mod error;
+mod frame;
+mod indexed;
+mod max_size;
+mod options;
+mod raw;
+mod schema;
 mod ser;
-mod varint;
+pub mod varint;
 
+pub use byte_array::ByteArray;
+pub use compress::DeltaSeq;
 pub use de::deserializer::Deserializer;
-pub use de::{from_bytes, take_from_bytes};
+pub use de::limits::{DeConfig, Limits};
+
+#[cfg(feature = "use-std")]
+pub use de::from_reader;
+pub use de::{
+    decode_vec_partial, from_bytes, from_bytes_be, from_bytes_config, from_bytes_in_place,
+    from_bytes_into_string, from_bytes_seed, from_bytes_strict, from_bytes_with_options,
+    from_mut_slice, split_from_bytes, take_from_bytes, take_from_bytes_seed, Needed,
+};
+pub use dict::Dict;
 pub use error::{Error, Result};
-pub use ser::{serializer::Serializer, to_slice, to_vec};
+pub use frame::{
+    decode_last, framed_iter, from_bytes_netenvelope, from_bytes_tagged, from_slice_fixed,
+    take_framed, to_slice_fixed, to_vec_footer, to_vec_framed, to_vec_netenvelope, to_vec_tagged,
+    FOOTER_LEN, FORMAT_VERSION, TAG_MAGIC,
+};
+pub use indexed::{to_vec_indexed, IndexedSeq};
+pub use max_size::{max_size, MaxSize};
+pub use options::{Endianness, Options, Width};
+pub use raw::RawBytes;
+pub use schema::{
+    from_bytes_with_schema, parse_with_schema, to_vec_dynamic, to_vec_with_schema, DynamicValue,
+    SchemaDesc,
+};
+pub use ser::output::{
+    CountingOutput, HashingOutput, RingOutput, SerOutput, SliceOutput, VecOutput,
+};
+pub use ser::{
+    hash_of, serialized_depth, serialized_size, serializer::Serializer, to_slice, to_slice_len,
+    to_slice_or_size, to_vec, to_vec_be, to_vec_canonical, to_vec_into, to_vec_with_capacity,
+    to_vec_with_options,
+};
+
+#[cfg(feature = "use-std")]
+pub use ser::{to_seek_writer, to_writer};
+
+#[cfg(feature = "heapless")]
+pub use ser::{output::HeaplessOutput, to_heapless_vec};
+
+#[cfg(feature = "bytes")]
+pub use ser::{output::BytesMutOutput, to_bytes};
+
+#[cfg(feature = "erased-serde")]
+pub use ser::to_vec_erased;
+
+#[cfg(feature = "crc")]
+pub use frame::{from_bytes_checked, from_bytes_crc32, to_vec_crc32};
@@ -0,0 +1,157 @@
+//! Upper bounds of the serialized size of fixed-shape types
+
+use core::marker::PhantomData;
+
+/// A type whose serialized size has a known upper bound
+///
+/// The bound holds for the default `Options`, and is meant for sizing
+/// static buffers, e.g. `[u8; max_size::<Reading>()]`. Types that have no
+/// bound, like `Vec` or `String`, don't implement this.
+///
+/// There is no derive. A struct adds up the bounds of its fields, and an
+/// enum takes its largest variant plus a byte for the discriminant, which
+/// is enough for up to 128 variants. Integers have their own `MAX`, which
+/// takes precedence over this one, so `max_size` is easier to use:
+///
+/// ```rust
+/// use pinecone::{max_size, to_vec, MaxSize};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Reading {
+///     sensor: u8,
+///     value: Option<u32>,
+/// }
+///
+/// impl MaxSize for Reading {
+///     const MAX: usize = max_size::<u8>() + max_size::<Option<u32>>();
+/// }
+///
+/// #[derive(Serialize)]
+/// enum Event {
+///     Reset,
+///     Reading(Reading),
+///     Range(u16, u16),
+/// }
+///
+/// impl MaxSize for Event {
+///     const MAX: usize = 1 + max(max_size::<Reading>(), max_size::<(u16, u16)>());
+/// }
+///
+/// const fn max(a: usize, b: usize) -> usize {
+///     if a > b { a } else { b }
+/// }
+///
+/// let event = Event::Reading(Reading { sensor: 1, value: Some(7) });
+/// let mut buf = [0u8; max_size::<Event>()];
+/// assert_eq!(max_size::<Event>(), 7);
+/// assert!(pinecone::to_slice(&event, &mut buf).is_ok());
+/// ```
+pub trait MaxSize {
+    /// The most bytes a value of this type takes when serialized
+    const MAX: usize;
+}
+
+/// The most bytes a `T` takes when serialized, see `MaxSize`
+pub const fn max_size<T>() -> usize
+where
+    T: MaxSize,
+{
+    T::MAX
+}
+
+macro_rules! impl_max_size {
+    ($($ty:ty => $max:expr),* $(,)?) => {
+        $(
+            impl MaxSize for $ty {
+                const MAX: usize = $max;
+            }
+        )*
+    };
+}
+
+impl_max_size! {
+    () => 0,
+    bool => 1,
+    u8 => 1,
+    u16 => 2,
+    u32 => 4,
+    u64 => 8,
+    u128 => 16,
+    usize => 8,
+    i8 => 1,
+    i16 => 2,
+    i32 => 4,
+    i64 => 8,
+    isize => 8,
+    i128 => 16,
+    f32 => 4,
+    f64 => 8,
+    char => 4,
+}
+
+impl<T: ?Sized> MaxSize for PhantomData<T> {
+    const MAX: usize = 0;
+}
+
+impl<T: MaxSize> MaxSize for Option<T> {
+    const MAX: usize = 1 + T::MAX;
+}
+
+// Arrays are written without a length prefix
+impl<T: MaxSize, const N: usize> MaxSize for [T; N] {
+    const MAX: usize = N * T::MAX;
+}
+
+macro_rules! impl_max_size_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: MaxSize),+> MaxSize for ($($name,)+) {
+            const MAX: usize = 0 $(+ $name::MAX)+;
+        }
+    };
+}
+
+impl_max_size_tuple!(A);
+impl_max_size_tuple!(A B);
+impl_max_size_tuple!(A B C);
+impl_max_size_tuple!(A B C D);
+impl_max_size_tuple!(A B C D E);
+impl_max_size_tuple!(A B C D E F);
+impl_max_size_tuple!(A B C D E F G);
+impl_max_size_tuple!(A B C D E F G H);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::serialized_size;
+
+    #[test]
+    fn primitives() {
+        assert_eq!(<u32 as MaxSize>::MAX, 4);
+        assert_eq!(max_size::<u128>(), 16);
+        assert_eq!(max_size::<()>(), 0);
+        assert_eq!(max_size::<Option<u16>>(), 3);
+        assert_eq!(max_size::<[u16; 5]>(), 10);
+
+        // Worst cases
+        assert_eq!(serialized_size(&i64::MIN), Ok(max_size::<i64>()));
+        assert_eq!(serialized_size(&i32::MIN), Ok(max_size::<i32>()));
+        assert_eq!(serialized_size(&i16::MIN), Ok(max_size::<i16>()));
+        assert_eq!(
+            serialized_size(&Some('\u{10FFFF}')),
+            Ok(max_size::<Option<char>>())
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(max_size::<(u8, u16, u32, u64)>(), 15);
+        assert_eq!(max_size::<((u8,), [bool; 3], Option<(u16, u16)>)>(), 9);
+
+        let value = (1u8, Some([2u16; 4]), 3.0f64);
+        assert_eq!(
+            serialized_size(&value),
+            Ok(max_size::<(u8, Option<[u16; 4]>, f64)>())
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Settings for the encoding
+
+/// Settings that change the encoding of some types
+///
+/// Data must be deserialized with the same options it was serialized with.
+///
+/// ```rust
+/// use pinecone::{Endianness, Options, Width};
+///
+/// let options = Options::new()
+///     .enum_discriminant_width(Width::U16)
+///     .endianness(Endianness::Big);
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Options {
+    pub(crate) enum_discriminant: Width,
+    pub(crate) length: Width,
+    pub(crate) endianness: Endianness,
+    pub(crate) lenient_option: bool,
+    pub(crate) canonical_maps: bool,
+    pub(crate) canonical_nan: bool,
+    pub(crate) varint_signed: bool,
+}
+
+impl Options {
+    /// The default encoding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How enum discriminants, i.e. variant indices, are encoded
+    ///
+    /// By default they are varints, so the first 128 variants take a single
+    /// byte, and new variants can be added without changing the encoding of
+    /// the others. A fixed width fails with `SerializeBadEnum` for indices
+    /// that do not fit.
+    pub fn enum_discriminant_width(mut self, width: Width) -> Self {
+        self.enum_discriminant = width;
+        self
+    }
+
+    /// How the lengths of strings, byte slices, sequences and maps are encoded
+    pub fn length_width(mut self, width: Width) -> Self {
+        self.length = width;
+        self
+    }
+
+    /// Byte order of fixed-size integers, from `u16` and `i16` up to
+    /// `u128` and `i128`. Varints, such as lengths, are not affected.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Accept any nonzero `Option` tag as `Some` when deserializing, instead
+    /// of only `1`. This only affects deserialization, for reading data from
+    /// producers that are less strict than pinecone.
+    pub fn lenient_option(mut self, lenient: bool) -> Self {
+        self.lenient_option = lenient;
+        self
+    }
+
+    /// Write map entries sorted by the bytes of their serialized keys, so
+    /// that equal maps always serialize the same, whatever their iteration
+    /// order. This only affects serialization, and nothing but maps.
+    ///
+    /// `Dict` values cannot be used inside such maps.
+    pub fn canonical_maps(mut self, canonical: bool) -> Self {
+        self.canonical_maps = canonical;
+        self
+    }
+
+    /// Write every NaN `f32` and `f64` as the same bit pattern, that of
+    /// `f32::NAN` or `f64::NAN`, dropping any sign or payload. Other values
+    /// are written unchanged. This only affects serialization.
+    pub fn canonical_nan(mut self, canonical: bool) -> Self {
+        self.canonical_nan = canonical;
+        self
+    }
+
+    /// Write `i16`, `i32` and `i64` as zigzag varints instead of fixed-size
+    /// integers, so that values close to zero, positive or negative, take
+    /// few bytes. `i64::MIN` takes 10. This takes precedence over
+    /// `endianness` for these types, and `MaxSize` does not account for it.
+    pub fn varint_signed(mut self, varint: bool) -> Self {
+        self.varint_signed = varint;
+        self
+    }
+}
+
+/// Encoding of an integer
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Width {
+    /// A varint, taking as few bytes as the value needs
+    #[default]
+    Varint,
+    /// A fixed single byte
+    U8,
+    /// A fixed two-byte little-endian value
+    U16,
+    /// A fixed four-byte little-endian value
+    U32,
+}
+
+/// Byte order of a fixed-size integer
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Endianness {
+    /// Least significant byte first
+    #[default]
+    Little,
+    /// Most significant byte first, also known as network byte order
+    Big,
+}
@@ -0,0 +1,123 @@
+//! Bytes that were serialized earlier
+
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+/// Bytes that are written as they are, instead of being serialized again
+///
+/// This is meant for embedding a cached, already serialized value in a
+/// larger message. The bytes bypass the structure of the data, so they must
+/// be a complete encoding of the value the reader expects in their place.
+/// With `len_prefix` set, they are written like a byte slice instead, which
+/// lets the reader skip them or read them as `&[u8]`.
+///
+/// ```rust
+/// use pinecone::{to_vec, RawBytes};
+///
+/// let cached: Vec<u8> = to_vec(&("pine", 7u8)).unwrap();
+/// let ser: Vec<u8> = to_vec(&(1u8, RawBytes::new(&cached))).unwrap();
+/// assert_eq!(ser, to_vec(&(1u8, ("pine", 7u8))).unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct RawBytes<'a> {
+    pub bytes: &'a [u8],
+    /// Write the length before the bytes
+    pub len_prefix: bool,
+}
+
+impl<'a> RawBytes<'a> {
+    /// Bytes that are written without a length
+    pub fn new(bytes: &'a [u8]) -> Self {
+        RawBytes {
+            bytes,
+            len_prefix: false,
+        }
+    }
+
+    /// Bytes that are written with a length, like a byte slice
+    pub fn prefixed(bytes: &'a [u8]) -> Self {
+        RawBytes {
+            bytes,
+            len_prefix: true,
+        }
+    }
+}
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.len_prefix {
+            return serializer.serialize_bytes(self.bytes);
+        }
+
+        // A tuple is written without a length prefix
+        let mut tuple = serializer.serialize_tuple(self.bytes.len())?;
+        for byte in self.bytes.iter() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::output::VecOutput;
+    use crate::ser::serializer::Serializer as PineconeSerializer;
+    use crate::{from_bytes, to_vec};
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Header {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Message {
+        seq: u64,
+        header: Header,
+        body: Vec<u8>,
+    }
+
+    #[test]
+    fn splice() {
+        let message = Message {
+            seq: 300,
+            header: Header {
+                id: 7,
+                name: "pine".to_string(),
+            },
+            body: vec![1, 2, 3],
+        };
+        let whole: Vec<u8> = to_vec(&message).unwrap();
+        let cached: Vec<u8> = to_vec(&message.header).unwrap();
+
+        let spliced: Vec<u8> =
+            to_vec(&(message.seq, RawBytes::new(&cached), message.body.as_slice())).unwrap();
+        assert_eq!(spliced, whole);
+        assert_eq!(from_bytes(spliced.deref()), Ok(message));
+
+        // The same through the serializer directly
+        let mut serializer = PineconeSerializer::new(VecOutput::new());
+        serializer.serialize(&300u64).unwrap();
+        serializer.serialize_raw(&cached).unwrap();
+        serializer.serialize(&[1u8, 2, 3][..]).unwrap();
+        assert_eq!(serializer.release(), Ok(whole));
+    }
+
+    #[test]
+    fn prefixed() {
+        let cached: Vec<u8> = to_vec(&(1u16, 2u16)).unwrap();
+        let output: Vec<u8> = to_vec(&RawBytes::prefixed(&cached)).unwrap();
+        assert_eq!(output.deref(), &[0x04, 0x01, 0x00, 0x02, 0x00]);
+        assert_eq!(from_bytes::<&[u8]>(&output), Ok(cached.deref()));
+
+        let output: Vec<u8> = to_vec(&RawBytes::new(&[])).unwrap();
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,603 @@
+//! Self-describing archives
+//!
+//! serde does not expose the structure of a type, so the schema is given by
+//! the user as a `SchemaDesc`, and written in front of the data. A generic
+//! tool can then read the data as a `DynamicValue` without the Rust types.
+//!
+//! When the schema is known some other way, such as from a message type tag,
+//! plain pinecone data can be read and written as a `DynamicValue` with
+//! `from_bytes_with_schema` and `to_vec_dynamic`.
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use serde::de::{
+    DeserializeSeed, Deserializer as _, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{
+    Error as _, SerializeMap, SerializeSeq, SerializeTuple, SerializeTupleVariant, Serializer as _,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::de::deserializer::Deserializer;
+use crate::error::{Error, Result};
+use crate::ser::output::{SerOutput, VecOutput};
+use crate::ser::serializer::Serializer;
+
+use crate::prelude::*;
+
+/// Describes how a value is laid out in the serialized data
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SchemaDesc {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    Str,
+    Bytes,
+    Option(Box<SchemaDesc>),
+    /// Variable length sequence, such as a `Vec`
+    Seq(Box<SchemaDesc>),
+    /// Fixed length sequence, such as a tuple or an array
+    Tuple(Vec<SchemaDesc>),
+    /// Named fields, in declaration order
+    Struct(Vec<(String, SchemaDesc)>),
+    Map(Box<SchemaDesc>, Box<SchemaDesc>),
+    /// Variants in declaration order, with their payloads. A unit variant
+    /// has a `Unit` payload, and a struct variant a `Struct` one.
+    Enum(Vec<(String, SchemaDesc)>),
+}
+
+/// A value read using a `SchemaDesc`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicValue {
+    Unit,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<DynamicValue>>),
+    Seq(Vec<DynamicValue>),
+    Tuple(Vec<DynamicValue>),
+    Struct(Vec<(String, DynamicValue)>),
+    Map(Vec<(DynamicValue, DynamicValue)>),
+    /// Name of the variant, and its payload
+    Enum(String, Box<DynamicValue>),
+}
+
+/// Serialize a `T` to a `Vec<u8>`, preceded by the schema describing it
+///
+/// The schema is not checked against the value, so it must match how `T`
+/// is serialized.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{parse_with_schema, to_vec_with_schema, DynamicValue, SchemaDesc};
+///
+/// let ser: Vec<u8> = to_vec_with_schema(&SchemaDesc::U16, &0x1234u16).unwrap();
+/// assert_eq!(parse_with_schema(&ser), Ok(DynamicValue::U16(0x1234)));
+/// ```
+pub fn to_vec_with_schema<T>(schema: &SchemaDesc, value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(VecOutput::new());
+    schema.serialize(&mut serializer)?;
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Deserialize data written by `to_vec_with_schema`, using the schema
+/// in front of it. The unused portion (if any) of the byte slice is discarded
+pub fn parse_with_schema(s: &[u8]) -> Result<DynamicValue> {
+    let mut deserializer = Deserializer::from_bytes(s);
+    let schema = SchemaDesc::deserialize(&mut deserializer)?;
+    SchemaSeed(&schema).deserialize(&mut deserializer)
+}
+
+/// Deserialize plain data as a `DynamicValue`, using a schema that is not
+/// part of the data. The unused portion (if any) of the byte slice is discarded
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{from_bytes_with_schema, to_vec, to_vec_dynamic, DynamicValue, SchemaDesc};
+///
+/// let schema = SchemaDesc::Tuple(vec![SchemaDesc::U8, SchemaDesc::Str]);
+/// let ser: Vec<u8> = to_vec(&(7u8, "seven")).unwrap();
+///
+/// let value = from_bytes_with_schema(&schema, &ser).unwrap();
+/// assert_eq!(
+///     value,
+///     DynamicValue::Tuple(vec![DynamicValue::U8(7), DynamicValue::Str("seven".to_string())])
+/// );
+/// assert_eq!(to_vec_dynamic(&schema, &value), Ok(ser));
+/// ```
+pub fn from_bytes_with_schema(schema: &SchemaDesc, s: &[u8]) -> Result<DynamicValue> {
+    let mut deserializer = Deserializer::from_bytes(s);
+    SchemaSeed(schema).deserialize(&mut deserializer)
+}
+
+/// Serialize a `DynamicValue` to a `Vec<u8>` as plain data, laid out as
+/// described by the schema
+///
+/// The result is the same as serializing the Rust type the schema describes.
+/// Fails with `SerdeSerCustom` if the value does not match the schema.
+pub fn to_vec_dynamic(schema: &SchemaDesc, value: &DynamicValue) -> Result<Vec<u8>> {
+    let mut serializer = Serializer::new(VecOutput::new());
+    SchemaValue(schema, value).serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Serializes a `DynamicValue`, which must match the schema
+struct SchemaValue<'s>(&'s SchemaDesc, &'s DynamicValue);
+
+impl<'s> Serialize for SchemaValue<'s> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use DynamicValue as V;
+        // Names are not written, so the serde ones are left empty
+        match (self.0, self.1) {
+            (SchemaDesc::Unit, V::Unit) => serializer.serialize_unit(),
+            (SchemaDesc::Bool, V::Bool(v)) => serializer.serialize_bool(*v),
+            (SchemaDesc::U8, V::U8(v)) => serializer.serialize_u8(*v),
+            (SchemaDesc::U16, V::U16(v)) => serializer.serialize_u16(*v),
+            (SchemaDesc::U32, V::U32(v)) => serializer.serialize_u32(*v),
+            (SchemaDesc::U64, V::U64(v)) => serializer.serialize_u64(*v),
+            (SchemaDesc::U128, V::U128(v)) => serializer.serialize_u128(*v),
+            (SchemaDesc::I8, V::I8(v)) => serializer.serialize_i8(*v),
+            (SchemaDesc::I16, V::I16(v)) => serializer.serialize_i16(*v),
+            (SchemaDesc::I32, V::I32(v)) => serializer.serialize_i32(*v),
+            (SchemaDesc::I64, V::I64(v)) => serializer.serialize_i64(*v),
+            (SchemaDesc::I128, V::I128(v)) => serializer.serialize_i128(*v),
+            (SchemaDesc::F32, V::F32(v)) => serializer.serialize_f32(*v),
+            (SchemaDesc::F64, V::F64(v)) => serializer.serialize_f64(*v),
+            (SchemaDesc::Char, V::Char(v)) => serializer.serialize_char(*v),
+            (SchemaDesc::Str, V::Str(v)) => serializer.serialize_str(v),
+            (SchemaDesc::Bytes, V::Bytes(v)) => serializer.serialize_bytes(v),
+            (SchemaDesc::Option(_), V::Option(None)) => serializer.serialize_none(),
+            (SchemaDesc::Option(inner), V::Option(Some(v))) => {
+                serializer.serialize_some(&SchemaValue(inner, v))
+            }
+            (SchemaDesc::Seq(element), V::Seq(values)) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(&SchemaValue(element, v))?;
+                }
+                seq.end()
+            }
+            (SchemaDesc::Tuple(fields), V::Tuple(values)) if fields.len() == values.len() => {
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for (field, v) in fields.iter().zip(values) {
+                    tuple.serialize_element(&SchemaValue(field, v))?;
+                }
+                tuple.end()
+            }
+            (SchemaDesc::Struct(fields), V::Struct(values))
+                if fields.iter().map(|f| &f.0).eq(values.iter().map(|v| &v.0)) =>
+            {
+                // Structs are written like tuples
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for ((_, field), (_, v)) in fields.iter().zip(values) {
+                    tuple.serialize_element(&SchemaValue(field, v))?;
+                }
+                tuple.end()
+            }
+            (SchemaDesc::Map(key, value), V::Map(entries)) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(&SchemaValue(key, k), &SchemaValue(value, v))?;
+                }
+                map.end()
+            }
+            (SchemaDesc::Enum(variants), V::Enum(name, payload)) => {
+                let index = variants
+                    .iter()
+                    .position(|variant| &variant.0 == name)
+                    .ok_or_else(|| S::Error::custom("unknown variant"))?;
+                let index = u32::try_from(index).map_err(S::Error::custom)?;
+                match (&variants[index as usize].1, &**payload) {
+                    (SchemaDesc::Unit, V::Unit) => serializer.serialize_unit_variant("", index, ""),
+                    (SchemaDesc::Tuple(fields), V::Tuple(values))
+                        if fields.len() == values.len() =>
+                    {
+                        let mut tuple =
+                            serializer.serialize_tuple_variant("", index, "", values.len())?;
+                        for (field, v) in fields.iter().zip(values) {
+                            tuple.serialize_field(&SchemaValue(field, v))?;
+                        }
+                        tuple.end()
+                    }
+                    (SchemaDesc::Struct(fields), V::Struct(values))
+                        if fields.iter().map(|f| &f.0).eq(values.iter().map(|v| &v.0)) =>
+                    {
+                        let mut tuple =
+                            serializer.serialize_tuple_variant("", index, "", values.len())?;
+                        for ((_, field), (_, v)) in fields.iter().zip(values) {
+                            tuple.serialize_field(&SchemaValue(field, v))?;
+                        }
+                        tuple.end()
+                    }
+                    (schema, payload) => serializer.serialize_newtype_variant(
+                        "",
+                        index,
+                        "",
+                        &SchemaValue(schema, payload),
+                    ),
+                }
+            }
+            _ => Err(S::Error::custom("value does not match the schema")),
+        }
+    }
+}
+
+/// Deserializes a `DynamicValue` laid out as described by the schema
+struct SchemaSeed<'s>(&'s SchemaDesc);
+
+impl<'de, 's> DeserializeSeed<'de> for SchemaSeed<'s> {
+    type Value = DynamicValue;
+
+    fn deserialize<D>(self, deserializer: D) -> core::result::Result<DynamicValue, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use DynamicValue as V;
+        match self.0 {
+            SchemaDesc::Unit => <()>::deserialize(deserializer).map(|_| V::Unit),
+            SchemaDesc::Bool => bool::deserialize(deserializer).map(V::Bool),
+            SchemaDesc::U8 => u8::deserialize(deserializer).map(V::U8),
+            SchemaDesc::U16 => u16::deserialize(deserializer).map(V::U16),
+            SchemaDesc::U32 => u32::deserialize(deserializer).map(V::U32),
+            SchemaDesc::U64 => u64::deserialize(deserializer).map(V::U64),
+            SchemaDesc::U128 => u128::deserialize(deserializer).map(V::U128),
+            SchemaDesc::I8 => i8::deserialize(deserializer).map(V::I8),
+            SchemaDesc::I16 => i16::deserialize(deserializer).map(V::I16),
+            SchemaDesc::I32 => i32::deserialize(deserializer).map(V::I32),
+            SchemaDesc::I64 => i64::deserialize(deserializer).map(V::I64),
+            SchemaDesc::I128 => i128::deserialize(deserializer).map(V::I128),
+            SchemaDesc::F32 => f32::deserialize(deserializer).map(V::F32),
+            SchemaDesc::F64 => f64::deserialize(deserializer).map(V::F64),
+            SchemaDesc::Char => char::deserialize(deserializer).map(V::Char),
+            SchemaDesc::Str => String::deserialize(deserializer).map(V::Str),
+            SchemaDesc::Bytes => deserializer.deserialize_byte_buf(self),
+            SchemaDesc::Option(_) => deserializer.deserialize_option(self),
+            SchemaDesc::Seq(_) => deserializer.deserialize_seq(self),
+            SchemaDesc::Tuple(fields) => deserializer.deserialize_tuple(fields.len(), self),
+            // Field names are not known statically, and are not needed
+            // for decoding, since structs are written like tuples
+            SchemaDesc::Struct(fields) => deserializer.deserialize_tuple(fields.len(), self),
+            SchemaDesc::Map(_, _) => deserializer.deserialize_map(self),
+            SchemaDesc::Enum(_) => deserializer.deserialize_enum("", &[], self),
+        }
+    }
+}
+
+impl<'de, 's> Visitor<'de> for SchemaSeed<'s> {
+    type Value = DynamicValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a value described by {:?}", self.0)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<DynamicValue, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(DynamicValue::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> core::result::Result<DynamicValue, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(DynamicValue::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> core::result::Result<DynamicValue, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(DynamicValue::Option(None))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> core::result::Result<DynamicValue, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match self.0 {
+            SchemaDesc::Option(inner) => {
+                let value = SchemaSeed(inner).deserialize(deserializer)?;
+                Ok(DynamicValue::Option(Some(Box::new(value))))
+            }
+            _ => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Option,
+                &self,
+            )),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<DynamicValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let missing = |i| serde::de::Error::invalid_length(i, &"more elements");
+        match self.0 {
+            SchemaDesc::Seq(element) => {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element_seed(SchemaSeed(element))? {
+                    values.push(value);
+                }
+                Ok(DynamicValue::Seq(values))
+            }
+            SchemaDesc::Tuple(fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (i, field) in fields.iter().enumerate() {
+                    let value = seq.next_element_seed(SchemaSeed(field))?;
+                    values.push(value.ok_or_else(|| missing(i))?);
+                }
+                Ok(DynamicValue::Tuple(values))
+            }
+            SchemaDesc::Struct(fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    let value = seq.next_element_seed(SchemaSeed(field))?;
+                    values.push((name.clone(), value.ok_or_else(|| missing(i))?));
+                }
+                Ok(DynamicValue::Struct(values))
+            }
+            _ => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Seq,
+                &self,
+            )),
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> core::result::Result<DynamicValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        match self.0 {
+            SchemaDesc::Map(key, value) => {
+                let mut entries = Vec::new();
+                while let Some(k) = map.next_key_seed(SchemaSeed(key))? {
+                    let v = map.next_value_seed(SchemaSeed(value))?;
+                    entries.push((k, v));
+                }
+                Ok(DynamicValue::Map(entries))
+            }
+            _ => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Map,
+                &self,
+            )),
+        }
+    }
+
+    fn visit_enum<A>(self, data: A) -> core::result::Result<DynamicValue, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let variants = match self.0 {
+            SchemaDesc::Enum(variants) => variants,
+            _ => {
+                return Err(serde::de::Error::invalid_type(
+                    serde::de::Unexpected::Enum,
+                    &self,
+                ))
+            }
+        };
+        let (index, variant): (u32, _) = data.variant()?;
+        let (name, payload) = variants.get(index as usize).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(index as u64),
+                &"a variant index",
+            )
+        })?;
+        let value = match payload {
+            SchemaDesc::Unit => variant.unit_variant().map(|_| DynamicValue::Unit)?,
+            SchemaDesc::Tuple(fields) => {
+                variant.tuple_variant(fields.len(), SchemaSeed(payload))?
+            }
+            SchemaDesc::Struct(fields) => {
+                variant.tuple_variant(fields.len(), SchemaSeed(payload))?
+            }
+            _ => variant.newtype_variant_seed(SchemaSeed(payload))?,
+        };
+        Ok(DynamicValue::Enum(name.clone(), Box::new(value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::to_vec;
+    use core::ops::Deref;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Point,
+        Circle(f32),
+        Rect { w: u16, h: u16 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Drawing {
+        name: String,
+        layer: Option<i8>,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<u8, String>,
+        raw: (char, bool),
+    }
+
+    fn drawing_schema() -> SchemaDesc {
+        let shape = SchemaDesc::Enum(vec![
+            ("Point".to_string(), SchemaDesc::Unit),
+            ("Circle".to_string(), SchemaDesc::F32),
+            (
+                "Rect".to_string(),
+                SchemaDesc::Struct(vec![
+                    ("w".to_string(), SchemaDesc::U16),
+                    ("h".to_string(), SchemaDesc::U16),
+                ]),
+            ),
+        ]);
+        SchemaDesc::Struct(vec![
+            ("name".to_string(), SchemaDesc::Str),
+            (
+                "layer".to_string(),
+                SchemaDesc::Option(Box::new(SchemaDesc::I8)),
+            ),
+            ("shapes".to_string(), SchemaDesc::Seq(Box::new(shape))),
+            (
+                "tags".to_string(),
+                SchemaDesc::Map(Box::new(SchemaDesc::U8), Box::new(SchemaDesc::Str)),
+            ),
+            (
+                "raw".to_string(),
+                SchemaDesc::Tuple(vec![SchemaDesc::Char, SchemaDesc::Bool]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn dynamic() {
+        let drawing = Drawing {
+            name: "sketch".to_string(),
+            layer: Some(-1),
+            shapes: vec![Shape::Point, Shape::Circle(0.5), Shape::Rect { w: 3, h: 4 }],
+            tags: [(1, "a".to_string())].iter().cloned().collect(),
+            raw: ('x', true),
+        };
+        let schema = drawing_schema();
+        let output: Vec<u8> = to_vec_with_schema(&schema, &drawing).unwrap();
+
+        // The data follows the schema unchanged
+        let header = to_vec(&schema).unwrap();
+        assert_eq!(&output[header.len()..], to_vec(&drawing).unwrap().deref());
+
+        let s = |v: &str| v.to_string();
+        let expected = DynamicValue::Struct(vec![
+            (s("name"), DynamicValue::Str(s("sketch"))),
+            (
+                s("layer"),
+                DynamicValue::Option(Some(Box::new(DynamicValue::I8(-1)))),
+            ),
+            (
+                s("shapes"),
+                DynamicValue::Seq(vec![
+                    DynamicValue::Enum(s("Point"), Box::new(DynamicValue::Unit)),
+                    DynamicValue::Enum(s("Circle"), Box::new(DynamicValue::F32(0.5))),
+                    DynamicValue::Enum(
+                        s("Rect"),
+                        Box::new(DynamicValue::Struct(vec![
+                            (s("w"), DynamicValue::U16(3)),
+                            (s("h"), DynamicValue::U16(4)),
+                        ])),
+                    ),
+                ]),
+            ),
+            (
+                s("tags"),
+                DynamicValue::Map(vec![(DynamicValue::U8(1), DynamicValue::Str(s("a")))]),
+            ),
+            (
+                s("raw"),
+                DynamicValue::Tuple(vec![DynamicValue::Char('x'), DynamicValue::Bool(true)]),
+            ),
+        ]);
+        assert_eq!(parse_with_schema(output.deref()), Ok(expected));
+    }
+
+    #[test]
+    fn dynamic_bytes() {
+        let data: (Vec<u8>, Option<u64>) = (vec![1, 2, 3], None);
+        let schema = SchemaDesc::Tuple(vec![
+            SchemaDesc::Bytes,
+            SchemaDesc::Option(Box::new(SchemaDesc::U64)),
+        ]);
+        let output: Vec<u8> = to_vec_with_schema(&schema, &data).unwrap();
+        assert_eq!(
+            parse_with_schema(output.deref()),
+            Ok(DynamicValue::Tuple(vec![
+                DynamicValue::Bytes(vec![1, 2, 3]),
+                DynamicValue::Option(None),
+            ]))
+        );
+    }
+
+    #[test]
+    fn out_of_band() {
+        let drawing = Drawing {
+            name: "plan".to_string(),
+            layer: None,
+            shapes: vec![Shape::Rect { w: 1, h: 2 }, Shape::Point],
+            tags: [(2, "b".to_string()), (9, "c".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+            raw: ('\u{e9}', false),
+        };
+        let schema = drawing_schema();
+        let output: Vec<u8> = to_vec(&drawing).unwrap();
+
+        let value = from_bytes_with_schema(&schema, output.deref()).unwrap();
+        assert_eq!(to_vec_dynamic(&schema, &value), Ok(output));
+
+        let expected_layer = ("layer".to_string(), DynamicValue::Option(None));
+        assert!(matches!(&value, DynamicValue::Struct(fields) if fields[1] == expected_layer));
+    }
+
+    #[test]
+    fn dynamic_mismatch() {
+        let value = DynamicValue::Tuple(vec![DynamicValue::Bool(true)]);
+        assert!(matches!(
+            to_vec_dynamic(&SchemaDesc::Tuple(vec![SchemaDesc::U8]), &value),
+            Err(Error::SerdeSerCustom(_))
+        ));
+
+        // A variant not in the schema, when writing and when reading
+        let schema = SchemaDesc::Enum(vec![("Only".to_string(), SchemaDesc::Unit)]);
+        let value = DynamicValue::Enum("Hexagon".to_string(), Box::new(DynamicValue::Unit));
+        assert!(matches!(
+            to_vec_dynamic(&schema, &value),
+            Err(Error::SerdeSerCustom(_))
+        ));
+        let output: Vec<u8> = to_vec_with_schema(&schema, &Shape::Circle(1.0)).unwrap();
+        assert!(matches!(
+            parse_with_schema(output.deref()),
+            Err(Error::SerdeDeCustom(_))
+        ));
+    }
+}
@@ -1,7 +1,10 @@
+use core::hash::Hasher;
+
 use serde::Serialize;
 
 use crate::error::{Error, Result};
-use crate::ser::output::{SerOutput, SliceOutput};
+use crate::options::{Endianness, Options};
+use crate::ser::output::{RingOutput, SerOutput, SliceOutput};
 use crate::ser::serializer::Serializer;
 
 use crate::prelude::*;
@@ -17,6 +20,10 @@ pub(crate) mod serializer;
 /// 1. A slice that contains the serialized message
 /// 2. A slice that contains the unused portion of the given buffer
 ///
+/// If the value does not fit, the buffer is only written up to the point
+/// where the write that failed would have started. Use `SliceOutput` with
+/// `Serializer::new` to find out where that is.
+///
 /// ## Example
 ///
 /// ```rust
@@ -38,13 +45,87 @@ pub(crate) mod serializer;
 /// let used = to_slice(data, &mut buf).unwrap();
 /// assert_eq!(used, &[0x01, 0x00, 0x20, 0x30]);
 /// ```
-pub fn to_slice<'a, 'b, T>(value: &'b T, buf: &'a mut [u8]) -> Result<&'a mut [u8]>
+pub fn to_slice<'a, T>(value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8]>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(SliceOutput::new(buf));
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Serialize a `T` to the given slice, like `to_slice`, but report how large
+/// the buffer needs to be if the value does not fit
+///
+/// The size is only computed when the buffer turns out to be too small,
+/// by serializing the value a second time without storing it.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{to_slice_or_size, Error};
+/// let mut buf = [0u8; 2];
+///
+/// assert_eq!(
+///     to_slice_or_size("Hi!", &mut buf),
+///     Err(Error::SerializeBufferTooSmall { needed: 4 })
+/// );
+/// ```
+pub fn to_slice_or_size<'a, T>(value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8]>
 where
     T: Serialize + ?Sized,
 {
-    let mut serializer = Serializer {
-        output: SliceOutput::new(buf),
+    // Borrowing `buf` for the result only on success keeps it usable below
+    let len = match to_slice_len(value, buf) {
+        Err(Error::SerializeBufferFull) => {
+            let needed = serialized_size(value)?;
+            return Err(Error::SerializeBufferTooSmall { needed });
+        }
+        result => result?,
     };
+    Ok(&mut buf[..len])
+}
+
+/// Serialize a `T` to the given slice, returning the number of bytes written
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_slice_len;
+/// let mut buf = [0u8; 32];
+///
+/// assert_eq!(to_slice_len("Hi!", &mut buf), Ok(4));
+/// assert_eq!(&buf[..4], &[0x03, b'H', b'i', b'!']);
+/// ```
+pub fn to_slice_len<T>(value: &T, buf: &mut [u8]) -> Result<usize>
+where
+    T: Serialize + ?Sized,
+{
+    // The used part of the slice ends where the output stopped writing
+    to_slice(value, buf).map(|used| used.len())
+}
+
+/// Serialize a `T` to a `heapless::Vec<u8, N>`, without allocating
+///
+/// Fails with `SerializeBufferFull` if the result does not fit in `N` bytes.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_heapless_vec;
+///
+/// let ser = to_heapless_vec::<4, _>("Hi!").unwrap();
+/// assert_eq!(ser.as_slice(), &[0x03, b'H', b'i', b'!']);
+/// ```
+#[cfg(feature = "heapless")]
+pub fn to_heapless_vec<const N: usize, T>(value: &T) -> Result<heapless::Vec<u8, N>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::HeaplessOutput::new());
     value.serialize(&mut serializer)?;
     serializer
         .output
@@ -52,8 +133,37 @@ where
         .map_err(|_| Error::SerializeBufferFull)
 }
 
+/// Serialize a `T` to a `bytes::Bytes`
+///
+/// The bytes are written into a `BytesMut`, which is then frozen, so unlike
+/// converting the result of `to_vec`, they are not copied again.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_bytes;
+///
+/// let ser = to_bytes("Hi!").unwrap();
+/// assert_eq!(&ser[..], &[0x03, b'H', b'i', b'!']);
+/// ```
+#[cfg(feature = "bytes")]
+pub fn to_bytes<T>(value: &T) -> Result<bytes::Bytes>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::BytesMutOutput::new());
+    value.serialize(&mut serializer)?;
+    serializer.release()
+}
+
 /// Serialize a `T` to a `Vec<u8>
 ///
+/// Sequences and maps whose length is not known up front are buffered, and
+/// their length is inserted in front of the elements once they are done.
+///
+/// If memory for the output can't be allocated, this fails with
+/// `Error::SerializeBufferFull` rather than aborting.
+///
 /// ## Example
 ///
 /// ```rust
@@ -69,9 +179,220 @@ pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
     T: Serialize + ?Sized,
 {
-    let mut serializer = Serializer {
-        output: output::VecOutput::new(),
-    };
+    let mut out = Vec::new();
+    to_vec_into(value, &mut out)?;
+    Ok(out)
+}
+
+/// Serialize a `T` to the end of an existing `Vec<u8>`, keeping what it
+/// already holds, such as a header
+///
+/// If serialization fails, `out` may have been partially written to.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_vec_into;
+///
+/// let mut out = vec![0xAA];
+/// to_vec_into("Hi!", &mut out).unwrap();
+/// assert_eq!(out.as_slice(), &[0xAA, 0x03, b'H', b'i', b'!']);
+/// ```
+pub fn to_vec_into<T>(value: &T, out: &mut Vec<u8>) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::AppendOutput(out));
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Serialize a type-erased value to a `Vec<u8>`
+///
+/// Trait objects of `erased_serde::Serialize` can also be passed to
+/// `to_vec` and the other functions directly. This saves spelling out the
+/// `dyn` type where the object is not already one.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{to_vec, to_vec_erased};
+///
+/// let plugins: Vec<Box<dyn erased_serde::Serialize>> = vec![Box::new(7u8), Box::new("Hi!")];
+/// let ser: Vec<u8> = to_vec_erased(plugins[1].as_ref()).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x03, b'H', b'i', b'!']);
+/// assert_eq!(to_vec(&plugins).unwrap().as_slice(), &[0x02, 0x07, 0x03, b'H', b'i', b'!']);
+/// ```
+#[cfg(feature = "erased-serde")]
+pub fn to_vec_erased(value: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+    to_vec(value)
+}
+
+/// Serialize a `T` to a `Vec<u8>` using the given encoding options
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::{to_vec_with_options, Options, Width};
+///
+/// let options = Options::new().enum_discriminant_width(Width::U16);
+/// let ser: Vec<u8> = to_vec_with_options(&Ok::<u8, u8>(7), options).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x00, 0x00, 0x07]);
+/// ```
+pub fn to_vec_with_options<T>(value: &T, options: Options) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::VecOutput::new()).with_options(options);
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Serialize a `T` to a `Vec<u8>`, with fixed-size integers in big-endian
+/// byte order. Shorthand for `to_vec_with_options` with `Endianness::Big`
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_vec_be;
+///
+/// let ser: Vec<u8> = to_vec_be(&0xA5C7u16).unwrap();
+/// assert_eq!(ser.as_slice(), &[0xA5, 0xC7]);
+/// ```
+pub fn to_vec_be<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    to_vec_with_options(value, Options::new().endianness(Endianness::Big))
+}
+
+/// Serialize a `T` to a `Vec<u8>`, with the entries of maps sorted by their
+/// serialized keys and all NaNs written the same way, so that equal values
+/// give equal bytes. Shorthand for `to_vec_with_options` with
+/// `Options::canonical_maps` and `Options::canonical_nan`
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_vec_canonical;
+/// use std::collections::HashMap;
+///
+/// let map: HashMap<u8, bool> = [(2, true), (1, false)].iter().cloned().collect();
+/// let ser: Vec<u8> = to_vec_canonical(&map).unwrap();
+/// assert_eq!(ser.as_slice(), &[0x02, 0x01, 0x00, 0x02, 0x01]);
+/// ```
+pub fn to_vec_canonical<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let options = Options::new().canonical_maps(true).canonical_nan(true);
+    to_vec_with_options(value, options)
+}
+
+/// Serialize a `T` to a `Vec<u8>` that is allocated once, up front
+///
+/// The value is serialized twice: first to compute its size, then into a
+/// `Vec` of exactly that capacity. This avoids reallocating while the output
+/// grows, which pays off for large messages. The result is the same as with
+/// `to_vec`.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_vec_with_capacity;
+///
+/// let ser: Vec<u8> = to_vec_with_capacity("Hi!").unwrap();
+/// assert_eq!(ser.as_slice(), &[0x03, b'H', b'i', b'!']);
+/// ```
+pub fn to_vec_with_capacity<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    let size = serialized_size(value)?;
+    let mut serializer = Serializer::new(output::VecOutput::with_capacity(size));
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Compute the size of a serialized `T` without allocating
+///
+/// The size is exactly the length of the output of `to_vec`.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::serialized_size;
+///
+/// assert_eq!(serialized_size(&true), Ok(1));
+/// assert_eq!(serialized_size("Hi!"), Ok(4));
+/// ```
+pub fn serialized_size<T>(value: &T) -> Result<usize>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::CountingOutput::new());
+    value.serialize(&mut serializer)?;
+    serializer
+        .output
+        .release()
+        .map_err(|_| Error::SerializeBufferFull)
+}
+
+/// Compute how deeply a serialized `T` nests, without allocating
+///
+/// The depth is counted the same way as `Limits::max_depth`, so it is the
+/// smallest limit that allows deserializing the value. Sequences, maps,
+/// tuples, structs, newtype structs and enums each add a level, while an
+/// `Option` does not.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::serialized_depth;
+///
+/// assert_eq!(serialized_depth(&0u8), Ok(0));
+/// assert_eq!(serialized_depth(&vec![vec![0u8]]), Ok(2));
+/// ```
+pub fn serialized_depth<T>(value: &T) -> Result<usize>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::CountingOutput::new());
+    value.serialize(&mut serializer)?;
+    Ok(serializer.max_nesting())
+}
+
+/// Hash the serialized form of a `T` without allocating
+///
+/// The bytes are fed to `hasher` as they are serialized, so the hash is the
+/// same as hashing the output of `to_vec` with a hasher that does not depend
+/// on how its input is split.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::hash_of;
+/// use std::collections::hash_map::DefaultHasher;
+///
+/// let a = hash_of(&(1u8, "Hi!"), DefaultHasher::new()).unwrap();
+/// let b = hash_of(&(1u8, "Hi!"), DefaultHasher::new()).unwrap();
+/// assert_eq!(a, b);
+/// ```
+pub fn hash_of<T, H>(value: &T, hasher: H) -> Result<u64>
+where
+    T: Serialize + ?Sized,
+    H: Hasher,
+{
+    let mut serializer = Serializer::new(output::HashingOutput::new(hasher));
     value.serialize(&mut serializer)?;
     serializer
         .output
@@ -79,12 +400,76 @@ where
         .map_err(|_| Error::SerializeBufferFull)
 }
 
+/// Serialize a `T` into a writer, such as a `File` or a `TcpStream`
+///
+/// The bytes are streamed into the writer as they are produced, without
+/// buffering the whole message first. The writer is flushed on success.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_writer;
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// to_writer("Hi!", &mut out).unwrap();
+/// assert_eq!(out, &[0x03, b'H', b'i', b'!']);
+/// ```
+#[cfg(feature = "use-std")]
+pub fn to_writer<W, T>(value: &T, writer: &mut W) -> Result<()>
+where
+    W: std::io::Write,
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::WriteOutput::new(writer));
+    let result = value.serialize(&mut serializer);
+    // An IO error is reported instead of the error it caused
+    serializer
+        .output
+        .finish()
+        .map_err(|error| Error::Io(error.kind()))?;
+    result
+}
+
+/// Serialize a `T` into a seekable writer, such as a `File` or a `Cursor`
+///
+/// Like `to_vec`, this also supports sequences and maps whose length is not
+/// known before serializing them. As written data cannot be moved, a
+/// fixed-width length prefix is written in their place, and patched once all
+/// elements have been written. With varint lengths this prefix is five bytes
+/// wide, so such collections can hold at most `u32::MAX` elements.
+///
+/// ## Example
+///
+/// ```rust
+/// use pinecone::to_seek_writer;
+/// use std::io::Cursor;
+///
+/// let mut cursor = Cursor::new(Vec::new());
+/// to_seek_writer("Hi!", &mut cursor).unwrap();
+/// assert_eq!(cursor.get_ref(), &[0x03, b'H', b'i', b'!']);
+/// ```
+#[cfg(feature = "use-std")]
+pub fn to_seek_writer<W, T>(value: &T, writer: &mut W) -> Result<()>
+where
+    W: std::io::Write + std::io::Seek,
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(output::SeekWriterOutput::new(writer));
+    let result = value.serialize(&mut serializer);
+    serializer
+        .output
+        .finish()
+        .map_err(|error| Error::Io(error.kind()))?;
+    result
+}
+
 #[cfg(test)]
 mod test {
     #![allow(clippy::unreadable_literal)]
 
     use super::*;
-    use crate::varint::VarintUsize;
+    use crate::de::{from_bytes, from_bytes_with_options};
+    use crate::varint::{VarintI64, VarintUsize};
     use core::fmt::Write;
     use core::ops::Deref;
     use serde::Deserialize;
@@ -141,6 +526,66 @@ mod test {
         );
     }
 
+    /// Counts the writes made to a `VecOutput`
+    struct Writes {
+        inner: output::VecOutput,
+        pushes: usize,
+        extends: usize,
+    }
+
+    impl Writes {
+        fn new() -> Self {
+            Writes {
+                inner: output::VecOutput::new(),
+                pushes: 0,
+                extends: 0,
+            }
+        }
+    }
+
+    impl SerOutput for Writes {
+        type Output = (Vec<u8>, usize, usize);
+
+        fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+            self.extends += 1;
+            self.inner.try_extend(data)
+        }
+
+        fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+            self.pushes += 1;
+            self.inner.try_push(data)
+        }
+
+        fn release(self) -> core::result::Result<Self::Output, ()> {
+            Ok((self.inner.release()?, self.pushes, self.extends))
+        }
+    }
+
+    #[test]
+    fn bytes_bulk() {
+        let blob: Vec<u8> = (0..64 * 1024).map(|i| (i * 7) as u8).collect();
+        let mut serializer = Serializer::new(Writes::new());
+        serde_bytes::Bytes::new(&blob)
+            .serialize(&mut serializer)
+            .unwrap();
+        let (output, pushes, extends) = serializer.output.release().unwrap();
+
+        // The length prefix, and the data in one copy
+        assert_eq!((pushes, extends), (0, 2));
+        assert_eq!(output, to_vec(&blob).unwrap());
+        assert_eq!(
+            output,
+            to_vec(&serde_bytes::ByteBuf::from(blob.clone())).unwrap()
+        );
+
+        // Deserializing borrows the data from the input
+        let out: &[u8] = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, blob.deref());
+        assert_eq!(out.as_ptr(), output[3..].as_ptr());
+        let out: serde_bytes::ByteBuf = from_bytes(output.deref()).unwrap();
+        assert_eq!(out.into_vec(), blob);
+    }
+
     #[test]
     fn ser_byte_slice() {
         let input: &[u8] = &[1u8, 2, 3, 4, 5, 6, 7, 8];
@@ -190,7 +635,7 @@ mod test {
 
         assert!([1] == res);
 
-        let res = VarintUsize(usize::max_value()).to_buf(&mut buf);
+        let res = VarintUsize(usize::MAX).to_buf(&mut buf);
 
         // AJM TODO
         if VarintUsize::varint_usize_max() == 5 {
@@ -203,6 +648,84 @@ mod test {
         }
     }
 
+    #[test]
+    fn varint_prefix_writes() {
+        let strings: Vec<String> = (0..10_000).map(|i| "x".repeat(i % 300)).collect();
+        let mut serializer = Serializer::new(Writes::new());
+        serializer.serialize(&strings).unwrap();
+        let (output, pushes, extends) = serializer.release().unwrap();
+
+        // Each varint prefix is written at once, not byte by byte
+        assert_eq!((pushes, extends), (0, 1 + 2 * strings.len()));
+
+        let mut expected = Vec::new();
+        let mut buf = VarintUsize::new_buf();
+        expected.extend_from_slice(VarintUsize(strings.len()).to_buf(&mut buf));
+        for string in strings.iter() {
+            expected.extend_from_slice(VarintUsize(string.len()).to_buf(&mut buf));
+            expected.extend_from_slice(string.as_bytes());
+        }
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn i64_varint_encode() {
+        let mut buf = VarintI64::new_buf();
+        assert_eq!(&[0x00], VarintI64(0).to_buf(&mut buf));
+        assert_eq!(&[0x01], VarintI64(-1).to_buf(&mut buf));
+        assert_eq!(&[0x02], VarintI64(1).to_buf(&mut buf));
+        assert_eq!(&[0x7F], VarintI64(-64).to_buf(&mut buf));
+        assert_eq!(&[0x80, 0x01], VarintI64(64).to_buf(&mut buf));
+        assert_eq!(
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            VarintI64(i64::MIN).to_buf(&mut buf)
+        );
+
+        for &value in &[0, -1, 1, i64::MIN, i64::MAX] {
+            let zigzag = VarintI64(value).zigzag();
+            assert_eq!(VarintI64::from_zigzag(zigzag).0, value);
+        }
+    }
+
+    #[test]
+    fn signed_ints() {
+        assert_eq!(to_vec(&-1i16).unwrap(), &[0xFF; 2]);
+        assert_eq!(to_vec(&-1i32).unwrap(), &[0xFF; 4]);
+        assert_eq!(to_vec(&-1i64).unwrap(), &[0xFF; 8]);
+        assert_eq!(to_vec(&i64::MIN).unwrap(), &i64::MIN.to_le_bytes());
+
+        let options = Options::new().varint_signed(true);
+        assert_eq!(to_vec_with_options(&-1i16, options).unwrap(), &[0x01]);
+        assert_eq!(to_vec_with_options(&-1i32, options).unwrap(), &[0x01]);
+        assert_eq!(to_vec_with_options(&-1i64, options).unwrap(), &[0x01]);
+        assert_eq!(
+            to_vec_with_options(&i64::MIN, options).unwrap().len(),
+            VarintI64::MAX_LEN
+        );
+        // Byte order does not apply to varints
+        let big = options.endianness(Endianness::Big);
+        assert_eq!(to_vec_with_options(&-2i32, big).unwrap(), &[0x03]);
+
+        for options in [Options::new(), options] {
+            for &value in &[0, -1, 1, i64::MIN, i64::MAX] {
+                let output = to_vec_with_options(&value, options).unwrap();
+                assert_eq!(from_bytes_with_options::<i64>(&output, options), Ok(value));
+            }
+            for &value in &[0, -1, 1, i16::MIN, i16::MAX] {
+                let output = to_vec_with_options(&value, options).unwrap();
+                assert_eq!(from_bytes_with_options::<i16>(&output, options), Ok(value));
+            }
+        }
+
+        // A zigzag varint too large for the type
+        let options = Options::new().varint_signed(true);
+        let output = to_vec_with_options(&(i64::from(i16::MAX) + 1), options).unwrap();
+        assert_eq!(
+            from_bytes_with_options::<i16>(&output, options),
+            Err(Error::DeserializeBadVarint)
+        );
+    }
+
     #[allow(dead_code)]
     #[derive(Serialize)]
     enum BasicEnum {
@@ -232,16 +755,16 @@ mod test {
         let output: Vec<u8> = to_vec(&BasicEnum::Bim).unwrap();
         assert_eq!(&[0x01], output.deref());
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bim(u64::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bim(u64::MAX)).unwrap();
         assert_eq!(
             &[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
             output.deref()
         );
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bib(u16::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bib(u16::MAX)).unwrap();
         assert_eq!(&[0x00, 0xFF, 0xFF], output.deref());
 
-        let output: Vec<u8> = to_vec(&DataEnum::Bap(u8::max_value())).unwrap();
+        let output: Vec<u8> = to_vec(&DataEnum::Bap(u8::MAX)).unwrap();
         assert_eq!(&[0x02, 0xFF], output.deref());
 
         let output: Vec<u8> = to_vec(&DataEnum::Kim(EnumStruct {
@@ -353,6 +876,194 @@ mod test {
         assert_eq!(&[0x06, b'h', b'e', b'l', b'L', b'O', b'!'], output.deref());
     }
 
+    #[test]
+    fn vec_with_capacity() {
+        let input: Vec<u32> = (0..(1 << 18)).collect();
+        let output = to_vec_with_capacity(&input).unwrap();
+        assert_eq!(output, to_vec(&input).unwrap());
+        assert_eq!(output.capacity(), output.len());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_output() {
+        let input = RefStruct {
+            bytes: &[0x01, 0x10, 0x02, 0x20],
+            str_s: "hElLo",
+        };
+        let output = to_bytes(&input).unwrap();
+        assert_eq!(output.deref(), to_vec(&input).unwrap().deref());
+
+        // Lengths that are only known at the end are inserted in place
+        let unsized_seq = Unsized([1u8..3, 3..130].iter().map(|r| Unsized(r.clone())));
+        let output = to_bytes(&unsized_seq).unwrap();
+        assert_eq!(&output[..4], &[0x02, 0x02, 0x01, 0x02]);
+        assert_eq!(&output[4..6], &[0x7F, 0x03]);
+        assert_eq!(output.len(), 1 + 3 + 1 + 127);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_vec() {
+        let input: (u32, &str) = (0xA5A5_A5A5, "fill");
+        let expected = to_vec(&input).unwrap();
+        assert_eq!(expected.len(), 9);
+
+        let output = to_heapless_vec::<9, _>(&input).unwrap();
+        assert_eq!(output.as_slice(), expected.deref());
+        assert!(output.is_full());
+
+        assert_eq!(
+            to_heapless_vec::<8, _>(&input),
+            Err(Error::SerializeBufferFull)
+        );
+        // Single bytes are pushed separately from slices
+        assert_eq!(
+            to_heapless_vec::<4, _>(&(0u32, true)),
+            Err(Error::SerializeBufferFull)
+        );
+    }
+
+    #[test]
+    fn depth() {
+        use crate::de::deserializer::Deserializer;
+        use crate::de::limits::Limits;
+
+        fn check<T>(value: &T, expected: usize)
+        where
+            T: Serialize + for<'de> Deserialize<'de> + core::fmt::Debug + PartialEq,
+        {
+            let depth = serialized_depth(value).unwrap();
+            assert_eq!(depth, expected);
+
+            // The depth is exactly enough to deserialize the value
+            let output = to_vec(value).unwrap();
+            let limits = Limits {
+                max_depth: depth,
+                ..Limits::default()
+            };
+            let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+            assert_eq!(&T::deserialize(&mut de).unwrap(), value);
+            if depth > 0 {
+                let limits = Limits {
+                    max_depth: depth - 1,
+                    ..Limits::default()
+                };
+                let mut de = Deserializer::from_bytes(&output).with_limits(limits);
+                assert_eq!(T::deserialize(&mut de), Err(Error::DeserializeTooDeep));
+            }
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Shape {
+            Unit,
+            Newtype(u8),
+            Tuple(u8, Vec<u8>),
+            Struct { inner: Option<Box<Shape>> },
+        }
+
+        check(&Some(vec![Some(1u8), None]), 1);
+        check(&None::<Vec<Option<u8>>>, 0);
+        check(&Some(vec![Some(vec![1u8])]), 2);
+        check(&BTreeMap::from([(1u8, (2u8, vec![3u8]))]), 3);
+        check(&Shape::Unit, 1);
+        check(&Shape::Newtype(1), 1);
+        check(&Shape::Tuple(1, vec![]), 3);
+        check(
+            &Shape::Struct {
+                inner: Some(Box::new(Shape::Struct { inner: None })),
+            },
+            4,
+        );
+    }
+
+    #[test]
+    fn hash() {
+        // FNV-1a, which does not depend on how the input is split
+        struct Fnv(u64);
+
+        impl Hasher for Fnv {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for byte in bytes {
+                    self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01B3);
+                }
+            }
+        }
+
+        let fnv = || Fnv(0xCBF2_9CE4_8422_2325);
+
+        #[derive(Serialize, Clone)]
+        struct Entry {
+            name: String,
+            tags: Vec<u16>,
+            size: u64,
+        }
+
+        let entry = Entry {
+            name: "index".to_string(),
+            tags: vec![1, 0x1234],
+            size: 0x10_0000,
+        };
+        let expected = {
+            let mut hasher = fnv();
+            hasher.write(&to_vec(&entry).unwrap());
+            hasher.finish()
+        };
+        assert_eq!(hash_of(&entry, fnv()), Ok(expected));
+        assert_eq!(hash_of(&entry.clone(), fnv()), Ok(expected));
+
+        let mut changed = entry;
+        changed.size += 1;
+        assert_ne!(hash_of(&changed, fnv()), Ok(expected));
+    }
+
+    #[test]
+    fn rc_str() {
+        // Shared strings are written like any other string.
+        // Requires the `rc` feature of serde.
+        let expected = to_vec("interned").unwrap();
+
+        let rc: Rc<str> = Rc::from("interned");
+        let output: Vec<u8> = to_vec(&rc).unwrap();
+        assert_eq!(output, expected);
+        let out: Rc<str> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, rc);
+        assert!(!Rc::ptr_eq(&out, &rc));
+
+        let arc: Arc<str> = Arc::from("interned");
+        let output: Vec<u8> = to_vec(&arc).unwrap();
+        assert_eq!(output, expected);
+        let out: Arc<str> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, arc);
+
+        // Sharing is not preserved, each one is written in full
+        let shared = vec![arc.clone(), arc];
+        let output: Vec<u8> = to_vec(&shared).unwrap();
+        assert_eq!(output, to_vec(&vec!["interned"; 2]).unwrap());
+        let out: Vec<Arc<str>> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out, shared);
+        assert!(!Arc::ptr_eq(&out[0], &out[1]));
+    }
+
+    #[test]
+    fn binary_heap() {
+        let input: BinaryHeap<u32> = [5, 1, 0x1234_5678, 3, 1].iter().copied().collect();
+        let output: Vec<u8> = to_vec(&input).unwrap();
+
+        // Written as a sequence, in the internal order of the heap
+        assert_eq!(output[0], 5);
+        let as_vec: Vec<u32> = from_bytes(output.deref()).unwrap();
+        assert_eq!(&as_vec, &input.clone().into_vec());
+
+        let out: BinaryHeap<u32> = from_bytes(output.deref()).unwrap();
+        assert_eq!(out.peek(), Some(&0x1234_5678));
+        assert_eq!(out.into_sorted_vec(), input.into_sorted_vec());
+    }
+
     #[test]
     fn hashmap() {
         let mut input: HashMap<u8, u8> = HashMap::new();
@@ -366,8 +1077,440 @@ mod test {
 
         input.insert(20, 25);
         let output: Vec<u8> = to_vec(&input).unwrap();
-        assert!(
-            (&[2, 10, 15, 20, 25] == output.deref()) || (&[2, 20, 25, 10, 15] == output.deref())
+        assert!(([2, 10, 15, 20, 25] == output.deref()) || ([2, 20, 25, 10, 15] == output.deref()));
+    }
+
+    /// Serializes the items of an iterator whose length is not known up front
+    struct Unsized<I>(I);
+
+    impl<I> Serialize for Unsized<I>
+    where
+        I: Iterator + Clone,
+        I::Item: Serialize,
+    {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_seq(self.0.clone())
+        }
+    }
+
+    /// Serializes the entries of an iterator as a map of unknown length
+    struct UnsizedMap<I>(I);
+
+    impl<I, K, V> Serialize for UnsizedMap<I>
+    where
+        I: Iterator<Item = (K, V)> + Clone,
+        K: Serialize,
+        V: Serialize,
+    {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_map(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn unknown_length_seq() {
+        let input = Unsized((0u8..10).filter(|v| v % 3 == 0));
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(&[0x04, 0x00, 0x03, 0x06, 0x09], output.deref());
+        assert_eq!(serialized_size(&input), Ok(output.len()));
+        assert_eq!(to_vec_with_capacity(&input), Ok(output));
+
+        // The length is written in its shortest form, even when nested
+        let input = Unsized(
+            [(0u8..0), (0u8..200)]
+                .iter()
+                .map(|range| Unsized(range.clone().filter(|_| true))),
+        );
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        let expected: Vec<Vec<u8>> = vec![vec![], (0..200).collect()];
+        assert_eq!(output, to_vec(&expected).unwrap());
+
+        // Outputs that cannot move data still need the length up front
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            to_slice(&Unsized((0u8..3).filter(|_| true)), &mut buf).map(|used| used.len()),
+            Err(Error::SerializeLengthUnknown)
+        );
+    }
+
+    #[test]
+    fn canonical_maps() {
+        let mut forward = HashMap::new();
+        let mut backward = HashMap::new();
+        for i in 0u8..64 {
+            forward.insert(i, i.wrapping_mul(7));
+            backward.insert(63 - i, (63 - i).wrapping_mul(7));
+        }
+
+        let output: Vec<u8> = to_vec_canonical(&forward).unwrap();
+        assert_eq!(output, to_vec_canonical(&backward).unwrap());
+        let sorted: BTreeMap<u8, u8> = forward.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(output, to_vec(&sorted).unwrap());
+        let out: HashMap<u8, u8> = crate::from_bytes(&output).unwrap();
+        assert_eq!(out, forward);
+
+        // Nested maps are sorted too, and other types are left alone
+        let inner: HashMap<u8, HashMap<u8, u8>> = (0..4).map(|i| (i, forward.clone())).collect();
+        let nested = (vec![3u8, 1, 2], UnsizedMap(inner.iter().filter(|_| true)));
+        let expected = (
+            vec![3u8, 1, 2],
+            vec![
+                (0u8, sorted.clone()),
+                (1, sorted.clone()),
+                (2, sorted.clone()),
+                (3, sorted),
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>(),
+        );
+        assert_eq!(
+            to_vec_canonical(&nested).unwrap(),
+            to_vec(&expected).unwrap()
+        );
+
+        let mut dicts = BTreeMap::new();
+        dicts.insert(1u8, crate::Dict("shared"));
+        assert_eq!(to_vec_canonical(&dicts), Err(Error::WontImplement));
+    }
+
+    #[test]
+    fn canonical_nan() {
+        let quiet = f64::from_bits(0x7FF8_0000_0000_0001);
+        let negative = f64::from_bits(0xFFF0_0000_0000_0002);
+        assert!(quiet.is_nan() && negative.is_nan());
+        assert_ne!(to_vec(&quiet).unwrap(), to_vec(&negative).unwrap());
+
+        let output: Vec<u8> = to_vec_canonical(&quiet).unwrap();
+        assert_eq!(output, f64::NAN.to_le_bytes());
+        assert_eq!(output, to_vec_canonical(&negative).unwrap());
+
+        let payload = f32::from_bits(0x7FA0_0001);
+        assert_eq!(
+            to_vec_canonical(&(payload, 1.5f64, -0.0f32)).unwrap(),
+            to_vec(&(f32::NAN, 1.5f64, -0.0f32)).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_length_map() {
+        let mut known = BTreeMap::new();
+        for i in 0u16..150 {
+            known.insert(i, format!("{}", i));
+        }
+        let input = UnsizedMap(known.iter().filter(|_| true));
+        let output: Vec<u8> = to_vec(&input).unwrap();
+        assert_eq!(&[0x96, 0x01], &output[..2]);
+        assert_eq!(output, to_vec(&known).unwrap());
+
+        let out: BTreeMap<u16, String> = crate::from_bytes(&output).unwrap();
+        assert_eq!(out, known);
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn seek_writer_unknown_length_seq() {
+        use std::io::Cursor;
+
+        let input = Unsized((0u16..10).filter(|v| v % 3 == 0));
+        let mut cursor = Cursor::new(Vec::new());
+        to_seek_writer(&input, &mut cursor).unwrap();
+
+        let output = cursor.into_inner();
+        let len_width = VarintUsize::PADDED_LEN;
+        assert_eq!(output.len(), len_width + 4 * 2);
+        assert_eq!(&output[len_width..], &[0, 0, 3, 0, 6, 0, 9, 0]);
+
+        let out: Vec<u16> = crate::from_bytes(&output).unwrap();
+        assert_eq!(out, vec![0, 3, 6, 9]);
+
+        // Nested collections are patched independently
+        let input = vec![Unsized(0u8..0), Unsized(0u8..3)];
+        let mut cursor = Cursor::new(Vec::new());
+        to_seek_writer(&input, &mut cursor).unwrap();
+
+        let out: Vec<Vec<u8>> = crate::from_bytes(cursor.get_ref()).unwrap();
+        assert_eq!(out, vec![vec![], vec![0, 1, 2]]);
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn seek_writer_fixed_length() {
+        use crate::options::Width;
+        use std::io::Cursor;
+
+        let options = Options::new().length_width(Width::U32);
+        let mut cursor = Cursor::new(Vec::new());
+        let mut serializer =
+            Serializer::new(output::SeekWriterOutput::new(&mut cursor)).with_options(options);
+        Unsized(0u8..3).serialize(&mut serializer).unwrap();
+        serializer.output.finish().unwrap();
+
+        assert_eq!(
+            cursor.get_ref(),
+            &[0x03, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02]
+        );
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn writer() {
+        let input: Vec<u8> = (0..100_000).map(|i| (i & 0xFF) as u8).collect();
+        let mut output: Vec<u8> = Vec::new();
+        to_writer(&input, &mut output).unwrap();
+        assert_eq!(output, to_vec(&input).unwrap());
+
+        let input = RefStruct {
+            bytes: &[0x01, 0x10, 0x02, 0x20],
+            str_s: "hElLo",
+        };
+        let mut output: Vec<u8> = Vec::new();
+        to_writer(&input, &mut output).unwrap();
+        assert_eq!(output, to_vec(&input).unwrap());
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn writer_io_error() {
+        use std::io::{Cursor, ErrorKind};
+
+        // A cursor over a slice can't grow past its end
+        let mut buf = [0u8; 4];
+        let mut cursor = Cursor::new(&mut buf[..]);
+        assert_eq!(
+            to_writer("Too long", &mut cursor),
+            Err(Error::Io(ErrorKind::WriteZero))
+        );
+
+        // Other errors are still reported as is
+        let mut output: Vec<u8> = Vec::new();
+        assert_eq!(
+            to_writer(&Unsized((0u8..3).filter(|_| true)), &mut output),
+            Err(Error::SerializeLengthUnknown)
+        );
+    }
+
+    /// Element that fails to serialize when it holds an error
+    #[derive(Clone)]
+    struct Fallible(core::result::Result<u8, &'static str>);
+
+    impl Serialize for Fallible {
+        fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            match self.0 {
+                Ok(v) => serializer.serialize_u8(v),
+                Err(msg) => Err(serde::ser::Error::custom(msg)),
+            }
+        }
+    }
+
+    #[test]
+    fn seq_element_error() {
+        let input = vec![
+            Fallible(Ok(1)),
+            Fallible(Ok(2)),
+            Fallible(Err("third")),
+            Fallible(Ok(4)),
+        ];
+        let expected = || Error::SerdeSerCustom("third".to_string());
+
+        assert_eq!(to_vec(&input), Err(expected()));
+        assert_eq!(serialized_size(&input), Err(expected()));
+
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            to_slice(&input, &mut buf).map(|used| used.len()),
+            Err(expected())
+        );
+
+        // The error is the same when it happens inside a nested collection
+        let nested = vec![input.clone(), input];
+        assert_eq!(to_vec(&nested), Err(expected()));
+    }
+
+    #[test]
+    fn slice_len() {
+        fn check<T: Serialize + ?Sized>(value: &T) {
+            let mut buf = [0u8; 64];
+            let len = to_slice_len(value, &mut buf).unwrap();
+            assert_eq!(
+                Ok(len),
+                to_slice(value, &mut [0u8; 64]).map(|used| used.len())
+            );
+            assert_eq!(&buf[..len], to_vec(value).unwrap().deref());
+
+            // Exactly full, and one byte short
+            let mut exact = vec![0u8; len];
+            assert_eq!(to_slice_len(value, &mut exact), Ok(len));
+            if len > 0 {
+                assert_eq!(
+                    to_slice_len(value, &mut exact[..len - 1]),
+                    Err(Error::SerializeBufferFull)
+                );
+            }
+        }
+
+        check(&());
+        check(&0x1234_5678u32);
+        check("hElLo");
+        check(&vec![300u16, 2, 1]);
+        check(&RefStruct {
+            bytes: &[0x01, 0x10, 0x02, 0x20],
+            str_s: "hElLo",
+        });
+    }
+
+    #[test]
+    fn slice_or_size() {
+        let mut buf = [0u8; 2];
+        assert_eq!(
+            to_slice_or_size(&0x1234_5678u32, &mut buf),
+            Err(Error::SerializeBufferTooSmall { needed: 4 })
+        );
+        assert_eq!(
+            to_slice(&0x1234_5678u32, &mut buf),
+            Err(Error::SerializeBufferFull)
+        );
+
+        let mut buf = [0u8; 4];
+        let used = to_slice_or_size(&0x1234_5678u32, &mut buf).unwrap();
+        assert_eq!(used, &[0x78, 0x56, 0x34, 0x12]);
+
+        // Other errors are passed through
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            to_slice_or_size(&Unsized((0u8..3).filter(|_| true)), &mut buf),
+            Err(Error::SerializeLengthUnknown)
+        );
+    }
+
+    #[test]
+    fn slice_failure_offset() {
+        let mut buf = [0xEE; 8];
+        assert_eq!(
+            to_slice("too long for it", &mut buf),
+            Err(Error::SerializeBufferFull)
+        );
+        // The length prefix fit, but nothing after it was touched
+        assert_eq!(buf, [0x0F, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE, 0xEE]);
+
+        let mut buf = [0xEE; 8];
+        let mut serializer = Serializer::new(SliceOutput::new(&mut buf));
+        serializer.serialize(&(0x1234u16, "abc")).unwrap();
+        assert_eq!(serializer.output().written(), 6);
+        assert_eq!(
+            serializer.serialize(&0xAABB_CCDDu32),
+            Err(Error::SerializeBufferFull)
+        );
+        assert_eq!(serializer.output().written(), 6);
+        assert_eq!(&buf[6..], &[0xEE, 0xEE]);
+    }
+
+    #[test]
+    fn ring_wraparound() {
+        // Bytes 3 to 5 are yet to be consumed
+        let mut buf = [0xEE; 8];
+        let mut serializer = Serializer::new(RingOutput::new(&mut buf, 6, 3));
+        assert_eq!(serializer.output().free(), 4);
+        serializer.serialize(&(0xAABBu16, 0xCCu8, 0xDDu8)).unwrap();
+        assert_eq!(serializer.output().free(), 0);
+        assert_eq!(serializer.serialize(&1u8), Err(Error::SerializeBufferFull));
+        assert_eq!(serializer.release(), Ok((4, 2)));
+        assert_eq!(buf, [0xCC, 0xDD, 0xEE, 0xEE, 0xEE, 0xEE, 0xBB, 0xAA]);
+
+        // An empty ring, where the length prefix goes before the end
+        let mut buf = [0xEE; 8];
+        let mut serializer = Serializer::new(RingOutput::new(&mut buf, 7, 7));
+        assert_eq!(serializer.output().free(), 7);
+        serializer.serialize(&[1u8; 6][..]).unwrap();
+        assert_eq!(serializer.release(), Ok((7, 6)));
+        assert_eq!(buf, [0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0xEE, 0x06]);
+    }
+
+    #[test]
+    fn vec_into() {
+        let input = RefStruct {
+            bytes: &[0x01, 0x10, 0x02, 0x20],
+            str_s: "hElLo",
+        };
+        let mut out = vec![0xAA, 0xBB];
+        to_vec_into(&input, &mut out).unwrap();
+        assert_eq!(&out[..2], &[0xAA, 0xBB]);
+        assert_eq!(&out[2..], to_vec(&input).unwrap().deref());
+
+        // Lengths inserted later are placed after the existing bytes
+        let mut out = vec![0xAA, 0xBB];
+        to_vec_into(&Unsized((1u8..4).filter(|_| true)), &mut out).unwrap();
+        assert_eq!(out, [0xAA, 0xBB, 0x03, 0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn vec_growth() {
+        // Many single-byte pushes into a buffer that starts out empty
+        let values: Vec<u16> = (0..50_000).collect();
+        let mut serializer = Serializer::new(output::VecOutput::with_capacity(0));
+        serializer.serialize(&values).unwrap();
+        let output = serializer.release().unwrap();
+        assert_eq!(output.len(), 3 + 2 * values.len());
+        assert_eq!(&output[..5], &[0xD0, 0x86, 0x03, 0x00, 0x00]);
+        assert_eq!(from_bytes::<Vec<u16>>(output.deref()), Ok(values));
+    }
+
+    #[test]
+    fn reused_serializer() {
+        let messages = [
+            RefStruct {
+                bytes: &[0x01, 0x02],
+                str_s: "first",
+            },
+            RefStruct {
+                bytes: &[],
+                str_s: "",
+            },
+            RefStruct {
+                bytes: &[0xFF; 200],
+                str_s: "third",
+            },
+        ];
+
+        let mut serializer = Serializer::from_vec(Vec::new());
+        let mut capacity = 0;
+        for message in &messages {
+            serializer.reset();
+            message.serialize(&mut serializer).unwrap();
+            assert_eq!(serializer.finish(), to_vec(message).unwrap().deref());
+            capacity = capacity.max(serializer.output.0.capacity());
+        }
+
+        // The allocation is kept, and `Dict` literals don't leak between messages
+        serializer.reset();
+        assert!(serializer.finish().is_empty());
+        assert_eq!(serializer.output.0.capacity(), capacity);
+        for _ in 0..2 {
+            serializer.reset();
+            crate::Dict("repeated").serialize(&mut serializer).unwrap();
+            assert_eq!(serializer.finish()[0], 0);
+        }
+    }
+
+    #[cfg(feature = "use-std")]
+    #[test]
+    fn seek_writer_element_error() {
+        use std::io::Cursor;
+
+        let items = [Fallible(Ok(1)), Fallible(Ok(2)), Fallible(Err("third"))];
+        let input = Unsized(items.iter().filter(|_| true));
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(
+            to_seek_writer(&input, &mut cursor),
+            Err(Error::SerdeSerCustom("third".to_string()))
         );
     }
 }
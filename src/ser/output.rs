@@ -1,9 +1,20 @@
+use core::convert::TryFrom;
+use core::hash::Hasher;
 use core::ops::Index;
 use core::ops::IndexMut;
 
+#[cfg(feature = "use-std")]
+use std::io::{Seek, SeekFrom, Write};
+
 use crate::prelude::*;
 
 /// Generic serialization target
+///
+/// Implement this to serialize into other kinds of storage, using
+/// `Serializer::new`. A failure is reported as `Error::SerializeBufferFull`.
+// The error carries no information, as the serializer has only one way to
+// report it
+#[allow(clippy::result_unit_err)]
 pub trait SerOutput {
     /// Result of the serialization
     type Output;
@@ -20,6 +31,38 @@ pub trait SerOutput {
     /// Pushes a single byte to be stored
     fn try_push(&mut self, data: u8) -> core::result::Result<(), ()>;
 
+    /// Reserves a length prefix whose value is not known yet by writing
+    /// `placeholder`, returning a slot that is later passed to `try_patch_len`.
+    ///
+    /// Only outputs that can revisit already written data implement this.
+    /// For others, sequences and maps of unknown length cannot be serialized.
+    fn try_reserve_len(&mut self, _placeholder: &[u8]) -> core::result::Result<usize, ()> {
+        Err(())
+    }
+
+    /// Overwrites the placeholder reserved by `try_reserve_len` with the
+    /// encoded length, which has the same size.
+    fn try_patch_len(&mut self, _slot: usize, _encoded: &[u8]) -> core::result::Result<(), ()> {
+        Err(())
+    }
+
+    /// Marks where a length prefix that is not known yet belongs, without
+    /// writing anything, returning a slot that is later passed to
+    /// `try_insert_len`.
+    ///
+    /// Outputs that can move already written data implement this, so that
+    /// the length is written in its shortest form. Others fall back to
+    /// `try_reserve_len`.
+    fn try_mark_len(&mut self) -> core::result::Result<usize, ()> {
+        Err(())
+    }
+
+    /// Inserts the encoded length at the slot marked by `try_mark_len`,
+    /// moving everything written after it.
+    fn try_insert_len(&mut self, _slot: usize, _encoded: &[u8]) -> core::result::Result<(), ()> {
+        Err(())
+    }
+
     /// Finalizes the storage operation, and resolved into associated type.
     fn release(self) -> core::result::Result<Self::Output, ()>;
 }
@@ -36,6 +79,15 @@ impl<'a> SliceOutput<'a> {
     pub fn new(buf: &'a mut [u8]) -> Self {
         SliceOutput { buf, idx: 0 }
     }
+
+    /// Number of bytes written so far
+    ///
+    /// A write that does not fit is rejected as a whole, so after a failure
+    /// this is where that write would have started, and the buffer from
+    /// here on is untouched.
+    pub fn written(&self) -> usize {
+        self.idx
+    }
 }
 
 impl<'a> SerOutput for SliceOutput<'a> {
@@ -86,13 +138,96 @@ impl<'a> IndexMut<usize> for SliceOutput<'a> {
     }
 }
 
+/// Stores the serialized bytes into a ring buffer, wrapping around its end.
+/// Resolves into the number of bytes written and the new head position.
+///
+/// The bytes from `tail` up to `head` have not been consumed yet, and are
+/// never overwritten. As equal positions mean an empty buffer, one byte is
+/// always left free. Like with `SliceOutput`, sequences and maps of unknown
+/// length cannot be serialized.
+pub struct RingOutput<'a> {
+    buf: &'a mut [u8],
+    head: usize,
+    tail: usize,
+    written: usize,
+}
+
+impl<'a> RingOutput<'a> {
+    /// Create from a backing buffer, writing from `head` onwards
+    ///
+    /// Panics if `head` or `tail` is not within the buffer.
+    pub fn new(buf: &'a mut [u8], head: usize, tail: usize) -> Self {
+        assert!(
+            head < buf.len() && tail < buf.len(),
+            "ring position out of bounds"
+        );
+        RingOutput {
+            buf,
+            head,
+            tail,
+            written: 0,
+        }
+    }
+
+    /// Number of bytes that can still be written
+    pub fn free(&self) -> usize {
+        (self.tail + self.buf.len() - self.head - 1) % self.buf.len()
+    }
+}
+
+impl<'a> SerOutput for RingOutput<'a> {
+    type Output = (usize, usize);
+
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        if data.len() > self.free() {
+            return Err(());
+        }
+
+        let (end, start) = data.split_at(data.len().min(self.buf.len() - self.head));
+        self.buf[self.head..self.head + end.len()].copy_from_slice(end);
+        self.buf[..start.len()].copy_from_slice(start);
+
+        self.head = (self.head + data.len()) % self.buf.len();
+        self.written += data.len();
+        Ok(())
+    }
+
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.try_extend(&[data])
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok((self.written, self.head))
+    }
+}
+
 /// Wrapper type around a `Vec`.
+///
+/// Memory is reserved with `Vec::try_reserve`, so running out of it is
+/// reported as an error instead of aborting.
+#[derive(Default)]
 pub struct VecOutput(pub Vec<u8>);
 
 impl VecOutput {
+    /// Create with an empty `Vec`
     pub fn new() -> Self {
         Self(Vec::new())
     }
+
+    /// Create with room for at least `capacity` bytes
+    ///
+    /// ```rust
+    /// use pinecone::{Serializer, VecOutput};
+    ///
+    /// let mut serializer = Serializer::new(VecOutput::with_capacity(64));
+    /// serializer.serialize("pine").unwrap();
+    /// let output = serializer.release().unwrap();
+    /// assert_eq!(output, [0x04, b'p', b'i', b'n', b'e']);
+    /// assert!(output.capacity() >= 64);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
 }
 
 impl SerOutput for VecOutput {
@@ -100,21 +235,69 @@ impl SerOutput for VecOutput {
 
     #[inline(always)]
     fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.0.try_reserve(data.len()).map_err(|_| ())?;
         self.0.extend_from_slice(data);
         Ok(())
     }
 
     #[inline(always)]
     fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.0.try_reserve(1).map_err(|_| ())?;
         self.0.push(data);
         Ok(())
     }
 
+    fn try_mark_len(&mut self) -> core::result::Result<usize, ()> {
+        Ok(self.0.len())
+    }
+
+    fn try_insert_len(&mut self, slot: usize, encoded: &[u8]) -> core::result::Result<(), ()> {
+        self.0.try_reserve(encoded.len()).map_err(|_| ())?;
+        self.0.splice(slot..slot, encoded.iter().copied());
+        Ok(())
+    }
+
     fn release(self) -> core::result::Result<Self::Output, ()> {
         Ok(self.0)
     }
 }
 
+/// Appends to a borrowed `Vec`, after whatever it already holds. Like
+/// `VecOutput`, it fails instead of aborting when out of memory.
+pub struct AppendOutput<'a>(pub &'a mut Vec<u8>);
+
+impl<'a> SerOutput for AppendOutput<'a> {
+    type Output = ();
+
+    #[inline(always)]
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.0.try_reserve(data.len()).map_err(|_| ())?;
+        self.0.extend_from_slice(data);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.0.try_reserve(1).map_err(|_| ())?;
+        self.0.push(data);
+        Ok(())
+    }
+
+    fn try_mark_len(&mut self) -> core::result::Result<usize, ()> {
+        Ok(self.0.len())
+    }
+
+    fn try_insert_len(&mut self, slot: usize, encoded: &[u8]) -> core::result::Result<(), ()> {
+        self.0.try_reserve(encoded.len()).map_err(|_| ())?;
+        self.0.splice(slot..slot, encoded.iter().copied());
+        Ok(())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok(())
+    }
+}
+
 impl Index<usize> for VecOutput {
     type Output = u8;
 
@@ -128,3 +311,315 @@ impl IndexMut<usize> for VecOutput {
         &mut self.0[idx]
     }
 }
+
+/// Counts the serialized bytes without storing them.
+/// Resolves into the number of bytes.
+#[derive(Default)]
+pub struct CountingOutput {
+    count: usize,
+}
+
+impl CountingOutput {
+    /// Create with a count of zero
+    ///
+    /// ```rust
+    /// use pinecone::{CountingOutput, Serializer};
+    ///
+    /// let mut serializer = Serializer::new(CountingOutput::new());
+    /// serializer.serialize(&(1u8, "pine")).unwrap();
+    /// serializer.serialize(&0u32).unwrap();
+    /// assert_eq!(serializer.release(), Ok(1 + 5 + 4));
+    /// ```
+    pub fn new() -> Self {
+        Self { count: 0 }
+    }
+}
+
+impl SerOutput for CountingOutput {
+    type Output = usize;
+
+    #[inline(always)]
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.count += data.len();
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn try_push(&mut self, _data: u8) -> core::result::Result<(), ()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn try_mark_len(&mut self) -> core::result::Result<usize, ()> {
+        Ok(self.count)
+    }
+
+    fn try_insert_len(&mut self, _slot: usize, encoded: &[u8]) -> core::result::Result<(), ()> {
+        self.count += encoded.len();
+        Ok(())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok(self.count)
+    }
+}
+
+/// Stores the serialized bytes into a `heapless::Vec`, without allocating.
+/// Fails once the capacity `N` is exceeded.
+#[cfg(feature = "heapless")]
+#[derive(Default)]
+pub struct HeaplessOutput<const N: usize>(pub heapless::Vec<u8, N>);
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> HeaplessOutput<N> {
+    /// Create with an empty `heapless::Vec`
+    ///
+    /// ```rust
+    /// use pinecone::{Error, HeaplessOutput, Serializer};
+    ///
+    /// let mut serializer = Serializer::new(HeaplessOutput::<8>::new());
+    /// serializer.serialize(&(1u8, "pine")).unwrap();
+    /// assert_eq!(serializer.serialize("cone"), Err(Error::SerializeBufferFull));
+    /// ```
+    pub fn new() -> Self {
+        Self(heapless::Vec::new())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> SerOutput for HeaplessOutput<N> {
+    type Output = heapless::Vec<u8, N>;
+
+    #[inline(always)]
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.0.extend_from_slice(data)
+    }
+
+    #[inline(always)]
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.0.push(data).map_err(|_| ())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok(self.0)
+    }
+}
+
+/// Stores the serialized bytes into a `bytes::BytesMut`.
+/// Resolves into a frozen `Bytes`, without copying.
+#[cfg(feature = "bytes")]
+#[derive(Default)]
+pub struct BytesMutOutput(pub bytes::BytesMut);
+
+#[cfg(feature = "bytes")]
+impl BytesMutOutput {
+    /// Create with an empty `BytesMut`
+    ///
+    /// ```rust
+    /// use pinecone::{BytesMutOutput, Serializer};
+    ///
+    /// let mut serializer = Serializer::new(BytesMutOutput::new());
+    /// serializer.serialize("pine").unwrap();
+    /// let output: bytes::Bytes = serializer.release().unwrap();
+    /// assert_eq!(&output[..], &[0x04, b'p', b'i', b'n', b'e']);
+    /// ```
+    pub fn new() -> Self {
+        Self(bytes::BytesMut::new())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl SerOutput for BytesMutOutput {
+    type Output = bytes::Bytes;
+
+    #[inline(always)]
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.0.extend_from_slice(data);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.0.extend_from_slice(&[data]);
+        Ok(())
+    }
+
+    fn try_mark_len(&mut self) -> core::result::Result<usize, ()> {
+        Ok(self.0.len())
+    }
+
+    fn try_insert_len(&mut self, slot: usize, encoded: &[u8]) -> core::result::Result<(), ()> {
+        let tail = self.0.split_off(slot);
+        self.0.extend_from_slice(encoded);
+        self.0.unsplit(tail);
+        Ok(())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok(self.0.freeze())
+    }
+}
+
+/// Feeds the serialized bytes into a `Hasher`.
+/// Resolves into the final hash.
+///
+/// Bytes are written to the hasher in chunks as they are produced, so the
+/// hasher should give the same result regardless of how the data is split.
+pub struct HashingOutput<H: Hasher> {
+    hasher: H,
+}
+
+impl<H: Hasher> HashingOutput<H> {
+    /// Create feeding into the given hasher
+    ///
+    /// Several values can be hashed together, unlike with `hash_of`:
+    ///
+    /// ```rust
+    /// use pinecone::{hash_of, HashingOutput, Serializer};
+    /// use std::collections::hash_map::DefaultHasher;
+    ///
+    /// let mut serializer = Serializer::new(HashingOutput::new(DefaultHasher::new()));
+    /// serializer.serialize(&1u8).unwrap();
+    /// serializer.serialize("Hi!").unwrap();
+    /// let hash = serializer.release().unwrap();
+    /// assert_eq!(hash, hash_of(&(1u8, "Hi!"), DefaultHasher::new()).unwrap());
+    /// ```
+    pub fn new(hasher: H) -> Self {
+        Self { hasher }
+    }
+}
+
+impl<H: Hasher> SerOutput for HashingOutput<H> {
+    type Output = u64;
+
+    #[inline(always)]
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.hasher.write(data);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.hasher.write_u8(data);
+        Ok(())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        Ok(self.hasher.finish())
+    }
+}
+
+/// Streams the serialized bytes into a writer.
+///
+/// The writer is flushed on release. The first IO error encountered is
+/// kept, and reported by `finish` once serialization has stopped.
+#[cfg(feature = "use-std")]
+pub struct WriteOutput<W: Write> {
+    writer: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "use-std")]
+impl<W: Write> WriteOutput<W> {
+    /// Create from a given writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Flushes the writer, returning the first IO error encountered, if any
+    pub fn finish(mut self) -> std::io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.flush(),
+        }
+    }
+
+    fn check<T>(&mut self, result: std::io::Result<T>) -> core::result::Result<T, ()> {
+        result.map_err(|error| {
+            self.error.get_or_insert(error);
+        })
+    }
+}
+
+#[cfg(feature = "use-std")]
+impl<W: Write> SerOutput for WriteOutput<W> {
+    type Output = ();
+
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        let result = self.writer.write_all(data);
+        self.check(result)
+    }
+
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        let result = self.writer.write_all(&[data]);
+        self.check(result)
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        self.finish().map_err(|_| ())
+    }
+}
+
+/// Streams the serialized bytes into a seekable writer.
+///
+/// Length prefixes that are not known up front are written as fixed-width
+/// placeholders, and patched in place once the collection has been written.
+#[cfg(feature = "use-std")]
+pub struct SeekWriterOutput<W: Write + Seek> {
+    inner: WriteOutput<W>,
+}
+
+#[cfg(feature = "use-std")]
+impl<W: Write + Seek> SeekWriterOutput<W> {
+    /// Create from a given writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            inner: WriteOutput::new(writer),
+        }
+    }
+
+    /// Flushes the writer, returning the first IO error encountered, if any
+    pub fn finish(self) -> std::io::Result<()> {
+        self.inner.finish()
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> core::result::Result<u64, ()> {
+        let result = self.inner.writer.seek(pos);
+        self.inner.check(result)
+    }
+}
+
+#[cfg(feature = "use-std")]
+impl<W: Write + Seek> SerOutput for SeekWriterOutput<W> {
+    type Output = ();
+
+    fn try_extend(&mut self, data: &[u8]) -> core::result::Result<(), ()> {
+        self.inner.try_extend(data)
+    }
+
+    fn try_push(&mut self, data: u8) -> core::result::Result<(), ()> {
+        self.inner.try_push(data)
+    }
+
+    fn try_reserve_len(&mut self, placeholder: &[u8]) -> core::result::Result<usize, ()> {
+        let pos = self.seek(SeekFrom::Current(0))?;
+        let slot = usize::try_from(pos).map_err(|_| ())?;
+        self.try_extend(placeholder)?;
+        Ok(slot)
+    }
+
+    fn try_patch_len(&mut self, slot: usize, encoded: &[u8]) -> core::result::Result<(), ()> {
+        let end = self.seek(SeekFrom::Current(0))?;
+        self.seek(SeekFrom::Start(slot as u64))?;
+        self.try_extend(encoded)?;
+        self.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    fn release(self) -> core::result::Result<Self::Output, ()> {
+        self.inner.release()
+    }
+}
@@ -1,8 +1,14 @@
+use core::convert::TryFrom;
+
 use serde::{ser, Serialize};
 
+use crate::dict::DICT_TOKEN;
 use crate::error::{Error, Result};
-use crate::ser::output::SerOutput;
-use crate::varint::VarintUsize;
+use crate::options::{Endianness, Options, Width};
+use crate::ser::output::{SerOutput, VecOutput};
+use crate::varint::{VarintBuf, VarintI64, VarintUsize};
+
+use crate::prelude::*;
 
 /// A `serde` compatible serializer
 pub struct Serializer<F>
@@ -10,9 +16,389 @@ where
     F: SerOutput,
 {
     pub(crate) output: F,
+    options: Options,
+    /// Nesting level of the sequences and maps currently being serialized
+    depth: usize,
+    /// Sequences and maps whose length prefix is patched when they end
+    pending: Vec<PendingLen>,
+    /// Literals written for `Dict` values, with their index
+    dict: BTreeMap<Vec<u8>, usize>,
+    /// Set while serializing the literal of a `Dict` value
+    in_dict: bool,
+    /// Current and deepest nesting, counted like `Limits::max_depth`
+    nesting: usize,
+    max_nesting: usize,
+    /// Serialized entries of the map being written with `canonical_maps`
+    map_entries: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Set while serializing an entry of a map with `canonical_maps`
+    in_map_entry: bool,
+}
+
+/// A length prefix that was reserved before the length was known
+struct PendingLen {
+    depth: usize,
+    slot: usize,
+    count: usize,
+    /// Inserted at `slot` when done, instead of overwriting a placeholder
+    inserted: bool,
 }
 
-impl<'a, F> ser::Serializer for &'a mut Serializer<F>
+impl<F> Serializer<F>
+where
+    F: SerOutput,
+{
+    /// Obtain a Serializer that writes into the given output
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use pinecone::{to_vec, SerOutput, Serializer};
+    ///
+    /// /// Keeps the last byte written
+    /// struct LastByte(Option<u8>);
+    ///
+    /// impl SerOutput for LastByte {
+    ///     type Output = Option<u8>;
+    ///
+    ///     fn try_push(&mut self, data: u8) -> Result<(), ()> {
+    ///         self.0 = Some(data);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn release(self) -> Result<Option<u8>, ()> {
+    ///         Ok(self.0)
+    ///     }
+    /// }
+    ///
+    /// let mut serializer = Serializer::new(LastByte(None));
+    /// serializer.serialize(&(1u8, "pine")).unwrap();
+    /// assert_eq!(serializer.release(), Ok(Some(b'e')));
+    /// ```
+    pub fn new(output: F) -> Self {
+        Serializer {
+            output,
+            options: Options::default(),
+            depth: 0,
+            pending: Vec::new(),
+            dict: BTreeMap::new(),
+            in_dict: false,
+            nesting: 0,
+            max_nesting: 0,
+            map_entries: Vec::new(),
+            in_map_entry: false,
+        }
+    }
+
+    /// Serialize using the given encoding options
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Serialize a value to the output. Several values can be written one
+    /// after another, and `Dict` values are shared between them.
+    pub fn serialize<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    /// Write bytes to the output as they are, e.g. a sub-message that was
+    /// serialized earlier. This bypasses the structure of the data, so the
+    /// bytes must be a complete encoding of whatever the reader expects at
+    /// this point. See also `RawBytes`.
+    pub fn serialize_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.output
+            .try_extend(bytes)
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// The output being written to
+    pub fn output(&self) -> &F {
+        &self.output
+    }
+
+    /// Finish serializing, returning the result of the output
+    pub fn release(self) -> Result<F::Output> {
+        self.output
+            .release()
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Deepest nesting reached so far, i.e. the smallest `Limits::max_depth`
+    /// that allows deserializing the output
+    pub(crate) fn max_nesting(&self) -> usize {
+        self.max_nesting
+    }
+
+    /// Enters `levels` levels of nesting, as the deserializer does
+    fn enter(&mut self, levels: usize) {
+        self.nesting += levels;
+        self.max_nesting = self.max_nesting.max(self.nesting);
+    }
+
+    fn leave(&mut self, levels: usize) {
+        self.nesting -= levels;
+    }
+
+    /// Writes the length prefix of a sequence or a map. If the length is not
+    /// known, its position is marked or a slot is reserved from the output,
+    /// and the prefix is written there in `end_len`.
+    fn begin_len(&mut self, len: Option<usize>) -> Result<()> {
+        self.depth += 1;
+        match len {
+            Some(len) => self.serialize_len(len),
+            None => {
+                if let Ok(slot) = self.output.try_mark_len() {
+                    self.pending.push(PendingLen {
+                        depth: self.depth,
+                        slot,
+                        count: 0,
+                        inserted: true,
+                    });
+                    return Ok(());
+                }
+
+                let mut buf = VarintUsize::new_buf();
+                let placeholder = self.encode_len(0, true, &mut buf)?;
+                let slot = self
+                    .output
+                    .try_reserve_len(placeholder)
+                    .map_err(|_| Error::SerializeLengthUnknown)?;
+                self.pending.push(PendingLen {
+                    depth: self.depth,
+                    slot,
+                    count: 0,
+                    inserted: false,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Encodes a length in the configured width. Varints are padded to the
+    /// size of a `u32` if `padded`, so that they can be overwritten in place.
+    fn encode_len<'b>(&self, len: usize, padded: bool, buf: &'b mut VarintBuf) -> Result<&'b [u8]> {
+        match self.options.length {
+            Width::Varint if padded => Ok(VarintUsize(len).to_padded_buf(buf)?),
+            Width::Varint => Ok(VarintUsize(len).to_buf(buf)),
+            Width::U8 => {
+                buf[0] = u8::try_from(len).map_err(|_| Error::SerializeBadLength)?;
+                Ok(&buf[..1])
+            }
+            Width::U16 => {
+                let len = u16::try_from(len).map_err(|_| Error::SerializeBadLength)?;
+                buf[..2].copy_from_slice(&len.to_le_bytes());
+                Ok(&buf[..2])
+            }
+            Width::U32 => {
+                let len = u32::try_from(len).map_err(|_| Error::SerializeBadLength)?;
+                buf[..4].copy_from_slice(&len.to_le_bytes());
+                Ok(&buf[..4])
+            }
+        }
+    }
+
+    /// Writes the length prefix of a string, a byte slice, a sequence or a map
+    fn serialize_len(&mut self, len: usize) -> Result<()> {
+        let mut buf = VarintUsize::new_buf();
+        let encoded = self.encode_len(len, false, &mut buf)?;
+        self.output
+            .try_extend(encoded)
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Writes a varint directly into the output
+    fn serialize_varint(&mut self, value: usize) -> Result<()> {
+        let mut buf = VarintUsize::new_buf();
+        self.output
+            .try_extend(VarintUsize(value).to_buf(&mut buf))
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Writes a `Dict` value as a reference to an identical earlier value,
+    /// or as a literal that later ones can refer to
+    fn serialize_dict<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.in_dict {
+            // Values nested in a literal are always literals themselves
+            self.serialize_varint(0)?;
+            return value.serialize(self);
+        }
+        if self.in_map_entry {
+            // Entries are reordered, so references could point forwards
+            return Err(Error::WontImplement);
+        }
+
+        let mut literal = Serializer::new(VecOutput::new()).with_options(self.options);
+        literal.in_dict = true;
+        value.serialize(&mut literal)?;
+        self.max_nesting = self.max_nesting.max(self.nesting + literal.max_nesting);
+        let bytes = literal.output.0;
+
+        match self.dict.get(&bytes) {
+            Some(&index) => self.serialize_varint(index + 1),
+            None => {
+                self.serialize_varint(0)?;
+                self.output
+                    .try_extend(&bytes)
+                    .map_err(|_| Error::SerializeBufferFull)?;
+                let index = self.dict.len();
+                self.dict.insert(bytes, index);
+                Ok(())
+            }
+        }
+    }
+
+    /// Serializes a key or a value of a map with `canonical_maps` on its own,
+    /// so that the entries can be sorted before they are written
+    fn serialize_map_entry<T>(&mut self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut entry = Serializer::new(VecOutput::new()).with_options(self.options);
+        entry.in_dict = self.in_dict;
+        entry.in_map_entry = true;
+        value.serialize(&mut entry)?;
+        self.max_nesting = self.max_nesting.max(self.nesting + entry.max_nesting);
+        Ok(entry.output.0)
+    }
+
+    /// Writes an enum discriminant in the configured width
+    fn serialize_variant_index(&mut self, index: u32) -> Result<()> {
+        match self.options.enum_discriminant {
+            Width::Varint => self.serialize_varint(index as usize),
+            Width::U8 => {
+                let index = u8::try_from(index).map_err(|_| Error::SerializeBadEnum)?;
+                self.output
+                    .try_push(index)
+                    .map_err(|_| Error::SerializeBufferFull)
+            }
+            Width::U16 => {
+                let index = u16::try_from(index).map_err(|_| Error::SerializeBadEnum)?;
+                self.output
+                    .try_extend(&index.to_le_bytes())
+                    .map_err(|_| Error::SerializeBufferFull)
+            }
+            Width::U32 => self
+                .output
+                .try_extend(&index.to_le_bytes())
+                .map_err(|_| Error::SerializeBufferFull),
+        }
+    }
+
+    /// Writes a fixed-size integer, given as little-endian bytes,
+    /// in the configured byte order
+    fn serialize_int<const N: usize>(&mut self, mut bytes: [u8; N]) -> Result<()> {
+        if self.options.endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        self.output
+            .try_extend(&bytes)
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Writes a signed integer as a zigzag-encoded varint
+    fn serialize_zigzag(&mut self, value: i64) -> Result<()> {
+        let mut buf = VarintI64::new_buf();
+        self.output
+            .try_extend(VarintI64(value).to_buf(&mut buf))
+            .map_err(|_| Error::SerializeBufferFull)
+    }
+
+    /// Counts an element of the current sequence or map, if its length is pending
+    fn count_element(&mut self) {
+        match self.pending.last_mut() {
+            Some(pending) if pending.depth == self.depth => pending.count += 1,
+            _ => {}
+        }
+    }
+
+    fn end_len(&mut self) -> Result<()> {
+        if matches!(self.pending.last(), Some(pending) if pending.depth == self.depth) {
+            let pending = self.pending.pop().unwrap();
+            let mut buf = VarintUsize::new_buf();
+            let encoded = self.encode_len(pending.count, !pending.inserted, &mut buf)?;
+            if pending.inserted {
+                self.output.try_insert_len(pending.slot, encoded)
+            } else {
+                self.output.try_patch_len(pending.slot, encoded)
+            }
+            .map_err(|_| Error::SerializeBufferFull)?;
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+impl Serializer<VecOutput> {
+    /// Obtain a Serializer that writes into a `Vec`, which can be reused
+    /// for several messages with `reset`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use pinecone::{to_vec, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// let mut serializer = Serializer::from_vec(Vec::with_capacity(64));
+    /// for value in &[1u32, 300, 70000] {
+    ///     serializer.reset();
+    ///     value.serialize(&mut serializer).unwrap();
+    ///     assert_eq!(serializer.finish(), to_vec(value).unwrap().as_slice());
+    /// }
+    /// ```
+    ///
+    /// The serializer can be kept around between messages:
+    ///
+    /// ```rust
+    /// use pinecone::{Serializer, VecOutput};
+    /// use serde::Serialize;
+    ///
+    /// struct Sender {
+    ///     scratch: Serializer<VecOutput>,
+    /// }
+    ///
+    /// impl Sender {
+    ///     fn encode<T: Serialize + ?Sized>(&mut self, value: &T) -> &[u8] {
+    ///         self.scratch.reset();
+    ///         value.serialize(&mut self.scratch).unwrap();
+    ///         self.scratch.finish()
+    ///     }
+    /// }
+    ///
+    /// let mut sender = Sender { scratch: Serializer::from_vec(Vec::new()) };
+    /// assert_eq!(sender.encode(&300u16), &[0x2C, 0x01]);
+    /// assert_eq!(sender.encode("ab"), &[0x02, b'a', b'b']);
+    /// ```
+    pub fn from_vec(mut buf: Vec<u8>) -> Self {
+        buf.clear();
+        Serializer::new(VecOutput(buf))
+    }
+
+    /// Discards everything serialized so far, keeping the allocation
+    pub fn reset(&mut self) {
+        self.output.0.clear();
+        self.depth = 0;
+        self.pending.clear();
+        self.dict.clear();
+        self.in_dict = false;
+        self.nesting = 0;
+        self.max_nesting = 0;
+        self.map_entries.clear();
+        self.in_map_entry = false;
+    }
+
+    /// The bytes serialized since the last `reset`
+    pub fn finish(&self) -> &[u8] {
+        &self.output.0
+    }
+}
+
+impl<F> ser::Serializer for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -32,6 +418,11 @@ where
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
+    // Types like `IpAddr` use a compact binary form instead of a string
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     fn serialize_bool(self, v: bool) -> Result<()> {
         self.serialize_u8(if v { 1 } else { 0 })
     }
@@ -41,21 +432,28 @@ where
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        if self.options.varint_signed {
+            return self.serialize_zigzag(v.into());
+        }
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        if self.options.varint_signed {
+            return self.serialize_zigzag(v.into());
+        }
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        if self.options.varint_signed {
+            return self.serialize_zigzag(v);
+        }
+        self.serialize_int(v.to_le_bytes())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
@@ -65,30 +463,38 @@ where
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.output
-            .try_extend(&v.to_le_bytes())
-            .map_err(|_| Error::SerializeBufferFull)
+        self.serialize_int(v.to_le_bytes())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.serialize_int(v.to_le_bytes())
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
+        let v = if self.options.canonical_nan && v.is_nan() {
+            f32::NAN
+        } else {
+            v
+        };
         self.output
             .try_extend(&v.to_le_bytes())
             .map_err(|_| Error::SerializeBufferFull)
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
+        let v = if self.options.canonical_nan && v.is_nan() {
+            f64::NAN
+        } else {
+            v
+        };
         self.output
             .try_extend(&v.to_le_bytes())
             .map_err(|_| Error::SerializeBufferFull)
@@ -99,7 +505,7 @@ where
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        VarintUsize(v.len()).serialize(&mut *self)?;
+        self.serialize_len(v.len())?;
         self.output
             .try_extend(v.as_bytes())
             .map_err(|_| Error::SerializeBufferFull)?;
@@ -107,6 +513,7 @@ where
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.serialize_len(v.len())?;
         self.output
             .try_extend(v)
             .map_err(|_| Error::SerializeBufferFull)
@@ -138,14 +545,24 @@ where
         variant_index: u32,
         _variant: &'static str,
     ) -> Result<()> {
-        VarintUsize(variant_index as usize).serialize(self)
+        self.enter(1);
+        self.serialize_variant_index(variant_index)?;
+        self.leave(1);
+        Ok(())
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(self)
+        self.enter(1);
+        if name == DICT_TOKEN {
+            self.serialize_dict(value)?;
+        } else {
+            value.serialize(&mut *self)?;
+        }
+        self.leave(1);
+        Ok(())
     }
 
     fn serialize_newtype_variant<T>(
@@ -158,16 +575,21 @@ where
     where
         T: ?Sized + Serialize,
     {
-        VarintUsize(variant_index as usize).serialize(&mut *self)?;
-        value.serialize(self)
+        self.enter(1);
+        self.serialize_variant_index(variant_index)?;
+        value.serialize(&mut *self)?;
+        self.leave(1);
+        Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        VarintUsize(len.ok_or(Error::SerializeLengthUnknown)?).serialize(&mut *self)?;
+        self.enter(1);
+        self.begin_len(len)?;
         Ok(self)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.enter(1);
         Ok(self)
     }
 
@@ -176,6 +598,7 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
+        self.enter(1);
         Ok(self)
     }
 
@@ -186,16 +609,28 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        VarintUsize(variant_index as usize).serialize(&mut *self)?;
+        // The enum, and the tuple inside it
+        self.enter(2);
+        self.serialize_variant_index(variant_index)?;
         Ok(self)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        VarintUsize(len.ok_or(Error::SerializeLengthUnknown)?).serialize(&mut *self)?;
+        self.enter(1);
+        if self.options.canonical_maps {
+            // Keys and values go to their own serializers, so maps nested
+            // in them never use this one
+            self.map_entries.clear();
+            return Ok(self);
+        }
+        // Otherwise entries are written in iteration order, which is sorted
+        // by key for `BTreeMap`, so its bytes never depend on insertion order
+        self.begin_len(len)?;
         Ok(self)
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.enter(1);
         Ok(self)
     }
 
@@ -206,19 +641,21 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        VarintUsize(variant_index as usize).serialize(&mut *self)?;
+        // The enum, and the struct inside it
+        self.enter(2);
+        self.serialize_variant_index(variant_index)?;
         Ok(self)
     }
 
-    fn collect_str<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    fn collect_str<T>(self, _value: &T) -> Result<Self::Ok>
     where
-        T: core::fmt::Display,
+        T: ?Sized + core::fmt::Display,
     {
         unreachable!()
     }
 }
 
-impl<'a, F> ser::SerializeSeq for &'a mut Serializer<F>
+impl<F> ser::SerializeSeq for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -232,16 +669,19 @@ where
     where
         T: ?Sized + Serialize,
     {
+        self.count_element();
         value.serialize(&mut **self)
     }
 
     // Close the sequence.
     fn end(self) -> Result<()> {
+        self.end_len()?;
+        self.leave(1);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeTuple for &'a mut Serializer<F>
+impl<F> ser::SerializeTuple for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -256,11 +696,12 @@ where
     }
 
     fn end(self) -> Result<()> {
+        self.leave(1);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeTupleStruct for &'a mut Serializer<F>
+impl<F> ser::SerializeTupleStruct for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -275,11 +716,12 @@ where
     }
 
     fn end(self) -> Result<()> {
+        self.leave(1);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeTupleVariant for &'a mut Serializer<F>
+impl<F> ser::SerializeTupleVariant for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -294,11 +736,12 @@ where
     }
 
     fn end(self) -> Result<()> {
+        self.leave(2);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeMap for &'a mut Serializer<F>
+impl<F> ser::SerializeMap for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -309,6 +752,12 @@ where
     where
         T: ?Sized + Serialize,
     {
+        if self.options.canonical_maps {
+            let key = self.serialize_map_entry(key)?;
+            self.map_entries.push((key, Vec::new()));
+            return Ok(());
+        }
+        self.count_element();
         key.serialize(&mut **self)
     }
 
@@ -316,15 +765,37 @@ where
     where
         T: ?Sized + Serialize,
     {
+        if self.options.canonical_maps {
+            let value = self.serialize_map_entry(value)?;
+            if let Some(entry) = self.map_entries.last_mut() {
+                entry.1 = value;
+            }
+            return Ok(());
+        }
         value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<()> {
+        if self.options.canonical_maps {
+            let mut entries = core::mem::take(&mut self.map_entries);
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            self.serialize_len(entries.len())?;
+            for (key, value) in &entries {
+                self.output
+                    .try_extend(key)
+                    .and_then(|()| self.output.try_extend(value))
+                    .map_err(|_| Error::SerializeBufferFull)?;
+            }
+            self.leave(1);
+            return Ok(());
+        }
+        self.end_len()?;
+        self.leave(1);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeStruct for &'a mut Serializer<F>
+impl<F> ser::SerializeStruct for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -339,11 +810,12 @@ where
     }
 
     fn end(self) -> Result<()> {
+        self.leave(1);
         Ok(())
     }
 }
 
-impl<'a, F> ser::SerializeStructVariant for &'a mut Serializer<F>
+impl<F> ser::SerializeStructVariant for &mut Serializer<F>
 where
     F: SerOutput,
 {
@@ -358,6 +830,7 @@ where
     }
 
     fn end(self) -> Result<()> {
+        self.leave(2);
         Ok(())
     }
 }
@@ -1,8 +1,27 @@
-use serde::{Serialize, Serializer};
+//! The varint encoding used for lengths and enum discriminants
+//!
+//! Each byte holds seven bits of the value, least significant first, with
+//! the top bit set on all but the last byte. This is the same as LEB128.
+//!
+//! ```rust
+//! use pinecone::varint::VarintUsize;
+//!
+//! let mut buf = VarintUsize::new_buf();
+//! let header = VarintUsize(300).to_buf(&mut buf);
+//! assert_eq!(header, &[0xAC, 0x02]);
+//! assert_eq!(VarintUsize::from_slice(header), Ok((VarintUsize(300), &[][..])));
+//! ```
+
+use core::convert::TryFrom;
+
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+use crate::de::deserializer::Deserializer;
+use crate::error::{Error, Result};
 
 /// A wrapper type that exists as a `usize` at rest, but is serialized
 /// to or deserialized from a varint.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct VarintUsize(pub usize);
 
 impl Serialize for VarintUsize {
@@ -12,7 +31,12 @@ impl Serialize for VarintUsize {
     {
         let mut buf = Self::new_buf();
         let used_buf = self.to_buf(&mut buf);
-        serializer.serialize_bytes(used_buf)
+        // A tuple is written without a length prefix
+        let mut tuple = serializer.serialize_tuple(used_buf.len())?;
+        for byte in used_buf.iter() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
     }
 }
 
@@ -25,7 +49,16 @@ impl Serialize for VarintUsize {
 pub type VarintBuf = [u8; VarintUsize::varint_usize_max()];
 
 impl VarintUsize {
-    pub fn to_buf<'a, 'b>(&'a self, out: &'b mut VarintBuf) -> &'b mut [u8] {
+    /// Encodes the value into `out`, returning the bytes that were used
+    ///
+    /// ```rust
+    /// use pinecone::varint::VarintUsize;
+    ///
+    /// let mut buf = VarintUsize::new_buf();
+    /// assert_eq!(VarintUsize(127).to_buf(&mut buf), &[0x7F]);
+    /// assert_eq!(VarintUsize(128).to_buf(&mut buf), &[0x80, 0x01]);
+    /// ```
+    pub fn to_buf<'b>(&self, out: &'b mut VarintBuf) -> &'b mut [u8] {
         let mut value = self.0;
         for i in 0..Self::varint_usize_max() {
             out[i] = (value & 0x7F) as u8;
@@ -40,10 +73,61 @@ impl VarintUsize {
         &mut out[..]
     }
 
+    /// Number of bytes written by `to_padded_buf`, the longest encoding of
+    /// a `u32`
+    pub const PADDED_LEN: usize = 5;
+
+    /// Like `to_buf`, but always uses `PADDED_LEN` bytes by padding the value
+    /// with continuation bytes. The result decodes to the same value, which
+    /// allows writing a fixed-size placeholder and filling it in later.
+    ///
+    /// The padding is no longer than a `u32` varint, so that readers with a
+    /// 32 bit `usize` accept it. Fails with `Error::SerializeBadLength` if
+    /// the value does not fit a `u32`.
+    ///
+    /// ```rust
+    /// use pinecone::varint::VarintUsize;
+    ///
+    /// let mut buf = VarintUsize::new_buf();
+    /// let padded = VarintUsize(3).to_padded_buf(&mut buf).unwrap();
+    /// assert_eq!(padded, &[0x83, 0x80, 0x80, 0x80, 0x00]);
+    /// ```
+    pub fn to_padded_buf<'b>(&self, out: &'b mut VarintBuf) -> Result<&'b mut [u8]> {
+        let mut value = u32::try_from(self.0).map_err(|_| Error::SerializeBadLength)?;
+        let last = Self::PADDED_LEN - 1;
+        for byte in out[..last].iter_mut() {
+            *byte = (value & 0x7F) as u8 | 0x80;
+            value >>= 7;
+        }
+        out[last] = value as u8;
+        Ok(&mut out[..Self::PADDED_LEN])
+    }
+
+    /// Decodes a varint from the start of `bytes`, returning it and the
+    /// bytes after it
+    ///
+    /// Fails with `Error::DeserializeBadVarint` if the value does not fit a
+    /// `usize`, and with `Error::DeserializeUnexpectedEnd` if the bytes end
+    /// in the middle of it.
+    ///
+    /// ```rust
+    /// use pinecone::varint::VarintUsize;
+    /// use pinecone::Error;
+    ///
+    /// let (len, rest) = VarintUsize::from_slice(&[0x80, 0x01, 0xFF]).unwrap();
+    /// assert_eq!((len.0, rest), (128, &[0xFF][..]));
+    /// assert_eq!(VarintUsize::from_slice(&[0x80]), Err(Error::DeserializeUnexpectedEnd));
+    /// ```
+    pub fn from_slice(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        decode_usize(bytes).map(|(value, rest)| (VarintUsize(value), rest))
+    }
+
+    /// A buffer large enough for any encoded `usize`
     pub const fn new_buf() -> VarintBuf {
         [0u8; Self::varint_usize_max()]
     }
 
+    /// Number of bytes in the longest encoding, that of `usize::MAX`
     pub const fn varint_usize_max() -> usize {
         const BITS_PER_BYTE: usize = 8;
         const BITS_PER_VARINT_BYTE: usize = 7;
@@ -60,3 +144,222 @@ impl VarintUsize {
         roundup_bits / BITS_PER_VARINT_BYTE
     }
 }
+
+/// Decodes a varint from the start of `buf`, returning its value and the
+/// bytes after it. This is the inverse of `VarintUsize::to_buf`.
+///
+/// Fails with `Error::DeserializeBadVarint` if the value does not fit a
+/// `usize`, and with `Error::DeserializeUnexpectedEnd` if the bytes end
+/// in the middle of it.
+///
+/// ```rust
+/// use pinecone::varint::decode_usize;
+///
+/// assert_eq!(decode_usize(&[0xAC, 0x02, 0x07]), Ok((300, &[0x07][..])));
+/// ```
+pub fn decode_usize(buf: &[u8]) -> Result<(usize, &[u8])> {
+    let mut deserializer = Deserializer::from_bytes(buf);
+    let value = deserializer.try_take_varint()?;
+    Ok((value, deserializer.remaining()))
+}
+
+/// Decodes a varint holding an unsigned integer of at most `bits` bits,
+/// taking one byte at a time from `next_byte`
+///
+/// Returns `Ok(None)` if the encoding is too long, or carries bits past the
+/// limit, instead of truncating the value.
+pub(crate) fn decode_bits<E, F>(bits: u32, mut next_byte: F) -> core::result::Result<Option<u64>, E>
+where
+    F: FnMut() -> core::result::Result<u8, E>,
+{
+    debug_assert!(bits <= 64);
+    let mut out = 0u64;
+    for i in 0..bits.div_ceil(7) {
+        let byte = next_byte()?;
+        let part = (byte & 0x7F) as u64;
+        let shift = 7 * i;
+        if shift + (64 - part.leading_zeros()) > bits {
+            return Ok(None);
+        }
+        out |= part << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some(out));
+        }
+    }
+    Ok(None)
+}
+
+/// A wrapper type that exists as a `u64` at rest, but is serialized
+/// to or deserialized from a varint.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct VarintU64(pub u64);
+
+/// Type alias for the largest buffer needed to store
+/// a `u64` varint as bytes
+pub type VarintU64Buf = [u8; VarintU64::MAX_LEN];
+
+impl VarintU64 {
+    /// Number of bytes in the longest encoding, that of `u64::MAX`
+    pub const MAX_LEN: usize = 10;
+
+    /// Encodes the value into `out`, returning the bytes that were used
+    ///
+    /// ```rust
+    /// use pinecone::varint::VarintU64;
+    ///
+    /// let mut buf = VarintU64::new_buf();
+    /// assert_eq!(VarintU64(300).to_buf(&mut buf), &[0xAC, 0x02]);
+    /// ```
+    pub fn to_buf<'b>(&self, out: &'b mut VarintU64Buf) -> &'b mut [u8] {
+        let mut value = self.0;
+        for i in 0..Self::MAX_LEN {
+            out[i] = (value & 0x7F) as u8;
+            value >>= 7;
+            if value != 0 {
+                out[i] |= 0x80;
+            } else {
+                return &mut out[..=i];
+            }
+        }
+        debug_assert_eq!(value, 0);
+        &mut out[..]
+    }
+
+    /// A buffer large enough for any encoded `u64`
+    pub const fn new_buf() -> VarintU64Buf {
+        [0u8; Self::MAX_LEN]
+    }
+}
+
+/// A wrapper type for signed integers that are serialized to or
+/// deserialized from a zigzag-encoded varint.
+///
+/// Zigzag encoding maps 0, -1, 1, -2, 2, ... to 0, 1, 2, 3, 4, ..., so that
+/// values close to zero take few bytes regardless of their sign.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct VarintI64(pub i64);
+
+/// Type alias for the largest buffer needed to store
+/// an `i64` varint as bytes
+pub type VarintI64Buf = VarintU64Buf;
+
+impl VarintI64 {
+    /// Number of bytes in the longest encoding, that of `i64::MIN`
+    pub const MAX_LEN: usize = VarintU64::MAX_LEN;
+
+    /// The zigzag-encoded value
+    pub const fn zigzag(&self) -> u64 {
+        ((self.0 << 1) ^ (self.0 >> 63)) as u64
+    }
+
+    /// Decodes a zigzag-encoded value
+    pub const fn from_zigzag(value: u64) -> Self {
+        Self((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Encodes the zigzag-encoded value into `out`, returning the bytes
+    /// that were used
+    pub fn to_buf<'b>(&self, out: &'b mut VarintI64Buf) -> &'b mut [u8] {
+        VarintU64(self.zigzag()).to_buf(out)
+    }
+
+    /// A buffer large enough for any encoded `i64`
+    pub const fn new_buf() -> VarintI64Buf {
+        [0u8; Self::MAX_LEN]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn usize_roundtrip() {
+        let cases: &[(usize, &[u8])] = &[
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (16_384, &[0x80, 0x80, 0x01]),
+        ];
+        let mut buf = VarintUsize::new_buf();
+        for &(value, encoded) in cases {
+            assert_eq!(VarintUsize(value).to_buf(&mut buf), encoded);
+            assert_eq!(
+                VarintUsize::from_slice(encoded),
+                Ok((VarintUsize(value), &[][..]))
+            );
+        }
+
+        let max = VarintUsize(usize::MAX).to_buf(&mut buf).to_vec();
+        assert_eq!(max.len(), VarintUsize::varint_usize_max());
+        assert_eq!(
+            VarintUsize::from_slice(&max),
+            Ok((VarintUsize(usize::MAX), &[][..]))
+        );
+
+        // Padding decodes to the same value
+        let padded = VarintUsize(5).to_padded_buf(&mut buf).unwrap().to_vec();
+        assert_eq!(
+            VarintUsize::from_slice(&padded),
+            Ok((VarintUsize(5), &[][..]))
+        );
+    }
+
+    #[test]
+    fn padded_fits_u32() {
+        let mut buf = VarintUsize::new_buf();
+        for value in [0, 300, u32::MAX as usize] {
+            let padded = VarintUsize(value).to_padded_buf(&mut buf).unwrap();
+            assert_eq!(padded.len(), VarintUsize::PADDED_LEN);
+
+            // Readers with a 32 bit usize only accept this many bits
+            let mut deserializer = Deserializer::from_bytes(padded);
+            assert_eq!(deserializer.try_take_varint_bits(32), Ok(value as u64));
+            assert!(deserializer.remaining().is_empty());
+        }
+
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            VarintUsize(u32::MAX as usize + 1).to_padded_buf(&mut buf),
+            Err(Error::SerializeBadLength)
+        );
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            decode_usize(&[0xE5, 0x8E, 0x26, 0x01, 0x02]),
+            Ok((624_485, &[0x01, 0x02][..]))
+        );
+        assert_eq!(decode_usize(&[0x00]), Ok((0, &[][..])));
+
+        // The continuation bit is set on the last byte
+        assert_eq!(
+            decode_usize(&[0xE5, 0x8E]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        // Bits past those of a usize
+        let mut overflow = [0xFFu8; 10];
+        overflow[9] = 0x7F;
+        assert_eq!(decode_usize(&overflow), Err(Error::DeserializeBadVarint));
+    }
+
+    #[test]
+    fn usize_bad() {
+        let mut too_long = [0x80u8; 11];
+        too_long[10] = 0x00;
+        assert_eq!(
+            VarintUsize::from_slice(&too_long),
+            Err(Error::DeserializeBadVarint)
+        );
+        assert_eq!(
+            VarintUsize::from_slice(&[0xFF]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+        assert_eq!(
+            VarintUsize::from_slice(&[]),
+            Err(Error::DeserializeUnexpectedEnd)
+        );
+    }
+}
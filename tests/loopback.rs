@@ -2,13 +2,20 @@
 
 use core::fmt::Debug;
 use core::fmt::Write;
-use core::ops::Deref;
+use core::marker::PhantomData;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use core::num::{NonZeroU32, NonZeroU64, NonZeroU8};
+use core::ops::{Deref, RangeFull, RangeInclusive, RangeTo};
+use core::time::Duration;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use pinecone::{from_bytes, to_vec};
+use pinecone::{from_bytes, max_size, serialized_size, to_vec, ByteArray, Error};
 
 use hashbrown::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
 struct BasicU8S {
@@ -42,6 +49,28 @@ enum DataEnum {
     Sho(u16, u8),
 }
 
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct WideId {
+    kind: u8,
+    id: u128,
+    offset: i128,
+    seq: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct KeyedRecord {
+    #[serde(with = "serde_bytes")]
+    key: [u8; 32],
+    id: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct Ids {
+    small: NonZeroU8,
+    user: NonZeroU32,
+    session: Option<NonZeroU64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 struct NewTypeStruct(u32);
 
@@ -51,6 +80,7 @@ struct PairStruct(u8, u16);
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 struct TupleStruct((u8, u16));
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 struct RefStruct<'a> {
     bytes: &'a [u8],
@@ -66,6 +96,42 @@ struct IntMapping(HashMap<u8, u8>);
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 struct StringMapping(HashMap<String, String>);
 
+/// Neither `Serialize` nor `Deserialize`
+#[derive(Debug, Eq, PartialEq)]
+struct Opaque;
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct Marked<T> {
+    id: u8,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Unit enum with more variants than fit in a one-byte varint
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+enum Wide {
+    V0, V1, V2, V3, V4, V5, V6, V7, V8, V9,
+    V10, V11, V12, V13, V14, V15, V16, V17, V18, V19,
+    V20, V21, V22, V23, V24, V25, V26, V27, V28, V29,
+    V30, V31, V32, V33, V34, V35, V36, V37, V38, V39,
+    V40, V41, V42, V43, V44, V45, V46, V47, V48, V49,
+    V50, V51, V52, V53, V54, V55, V56, V57, V58, V59,
+    V60, V61, V62, V63, V64, V65, V66, V67, V68, V69,
+    V70, V71, V72, V73, V74, V75, V76, V77, V78, V79,
+    V80, V81, V82, V83, V84, V85, V86, V87, V88, V89,
+    V90, V91, V92, V93, V94, V95, V96, V97, V98, V99,
+    V100, V101, V102, V103, V104, V105, V106, V107, V108, V109,
+    V110, V111, V112, V113, V114, V115, V116, V117, V118, V119,
+    V120, V121, V122, V123, V124, V125, V126, V127, V128, V129,
+    V130, V131, V132, V133, V134, V135, V136, V137, V138, V139,
+    V140, V141, V142, V143, V144, V145, V146, V147, V148, V149,
+    V150, V151, V152, V153, V154, V155, V156, V157, V158, V159,
+    V160, V161, V162, V163, V164, V165, V166, V167, V168, V169,
+    V170, V171, V172, V173, V174, V175, V176, V177, V178, V179,
+    V180, V181, V182, V183, V184, V185, V186, V187, V188, V189,
+    V190, V191, V192, V193, V194, V195, V196, V197, V198, V199,
+}
+
 #[test]
 fn loopback() {
     // Basic types
@@ -80,6 +146,19 @@ fn loopback() {
         &[0xEF, 0xCD, 0xAB, 0x90, 0x78, 0x56, 0x34, 0x12],
     );
 
+    test_one(u128::MAX, &[0xFF; 16]);
+    test_one(
+        i128::MIN,
+        &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80],
+    );
+    test_one(
+        0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210u128,
+        &[
+            0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE, 0xEF, 0xCD, 0xAB, 0x89, 0x67, 0x45,
+            0x23, 0x01,
+        ],
+    );
+
     // Structs
     test_one(
         BasicU8S {
@@ -94,14 +173,30 @@ fn loopback() {
         ],
     );
 
+    test_one(
+        WideId {
+            kind: 0xA1,
+            id: 0x0F,
+            offset: -2,
+            seq: 0xBEEF,
+        },
+        &[
+            0xA1, // kind
+            0x0F, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // id
+            0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF, // offset
+            0xEF, 0xBE, // seq
+        ],
+    );
+
     // Enums
     test_one(BasicEnum::Bim, &[0x01]);
     test_one(
-        DataEnum::Bim(u64::max_value()),
+        DataEnum::Bim(u64::MAX),
         &[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
     );
-    test_one(DataEnum::Bib(u16::max_value()), &[0x00, 0xFF, 0xFF]);
-    test_one(DataEnum::Bap(u8::max_value()), &[0x02, 0xFF]);
+    test_one(DataEnum::Bib(u16::MAX), &[0x00, 0xFF, 0xFF]);
+    test_one(DataEnum::Bap(u8::MAX), &[0x02, 0xFF]);
     test_one(
         DataEnum::Kim(EnumStruct {
             eight: 0xF0,
@@ -150,10 +245,482 @@ fn loopback() {
     // Data containers
     test_opaque(IntMapping(HashMap::new()));
     test_opaque(StringMapping(HashMap::new()));
-    // test_opaque(IntMapping(hashmap! {1 => 2, 3 => 4}));
-    // test_opaque(StringMapping(
-    //     hashmap! {"a".to_string() => "b".to_string(), "cd".to_string() => "ef".to_string()},
-    // ));
+    test_opaque(IntMapping([(1, 2), (3, 4)].iter().cloned().collect()));
+    test_opaque(StringMapping(
+        [("a", "b"), ("cd", "ef")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    ));
+}
+
+#[test]
+fn serde_bytes_array() {
+    // Length-prefixed like any other byte blob, on both sides
+    let mut expected = vec![0x20];
+    expected.extend(0..32u8);
+    expected.push(0x07);
+
+    let mut key = [0u8; 32];
+    key.iter_mut().zip(0..).for_each(|(k, i)| *k = i);
+    test_one(KeyedRecord { key, id: 0x07 }, &expected);
+
+    // A blob of the wrong length is rejected
+    let mut short = vec![0x1F];
+    short.extend(0..31u8);
+    short.push(0x07);
+    assert!(from_bytes::<KeyedRecord>(&short).is_err());
+}
+
+#[test]
+fn non_zero() {
+    test_one(NonZeroU8::new(0xA5).unwrap(), &[0xA5]);
+    test_one(
+        NonZeroU32::new(0xCDAB3412).unwrap(),
+        &[0x12, 0x34, 0xAB, 0xCD],
+    );
+    test_one(NonZeroU64::new(u64::MAX).unwrap(), &[0xFF; 8]);
+
+    let ids = Ids {
+        small: NonZeroU8::new(1).unwrap(),
+        user: NonZeroU32::new(0x0100).unwrap(),
+        session: NonZeroU64::new(2),
+    };
+    let expected = [
+        0x01, // small
+        0x00, 0x01, 0x00, 0x00, // user
+        0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // session
+    ];
+    let mut corrupted = expected;
+    test_one(ids, &expected);
+
+    // A zeroed field is rejected by serde, and reported as a custom error
+    corrupted[2] = 0x00;
+    assert!(matches!(
+        from_bytes::<Ids>(&corrupted),
+        Err(Error::SerdeDeCustom(_))
+    ));
+}
+
+#[test]
+fn ip_addr() {
+    // Variant index followed by the raw address bytes
+    test_one(
+        IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+        &[0x00, 0xC0, 0xA8, 0x00, 0x01],
+    );
+    let mut expected = vec![0x01];
+    expected.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+    test_one(IpAddr::V6(Ipv6Addr::LOCALHOST), &expected);
+
+    // Address followed by the port
+    test_one(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0x1F90),
+        &[0x00, 0x7F, 0x00, 0x00, 0x01, 0x90, 0x1F],
+    );
+    test_one(
+        vec![
+            Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            None,
+            Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        ],
+        &[
+            0x03, // len
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // Some(V4)
+            0x00, // None
+            0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, // Some(V6)
+        ],
+    );
+
+    assert!(matches!(
+        from_bytes::<IpAddr>(&[0x02, 0x00, 0x00, 0x00, 0x00]),
+        Err(Error::SerdeDeCustom(_))
+    ));
+}
+
+#[test]
+fn arrays() {
+    // Elements follow each other without a length prefix
+    test_one(
+        ["a".to_string(), String::new(), "bc".to_string()],
+        &[0x01, b'a', 0x00, 0x02, b'b', b'c'],
+    );
+    test_one(
+        [Some(1u32), None, None, Some(0x12345678)],
+        &[
+            0x01, 0x01, 0x00, 0x00, 0x00, // Some(1)
+            0x00, // None
+            0x00, // None
+            0x01, 0x78, 0x56, 0x34, 0x12, // Some(0x12345678)
+        ],
+    );
+
+    // The decoded elements are dropped when a later one fails
+    assert_eq!(
+        from_bytes::<[String; 3]>(&[0x01, b'a', 0x01, b'b', 0x01, 0xFF]),
+        Err(Error::DeserializeBadUtf8)
+    );
+    assert_eq!(
+        from_bytes::<[String; 3]>(&[0x01, b'a', 0x01, b'b']),
+        Err(Error::DeserializeUnexpectedEnd)
+    );
+    assert_eq!(
+        from_bytes::<[Option<u32>; 4]>(&[0x00, 0x00, 0x02]),
+        Err(Error::DeserializeBadOption)
+    );
+}
+
+#[test]
+fn unit_elements() {
+    // Units take no space, so only the length is written
+    test_one(Vec::<()>::new(), &[0x00]);
+    test_one(vec![(), (), ()], &[0x03]);
+    test_one(vec![vec![(); 2], vec![]], &[0x02, 0x02, 0x00]);
+
+    let out: Vec<()> = from_bytes(&[0x80, 0x01]).unwrap();
+    assert_eq!(out.len(), 128);
+}
+
+#[test]
+fn phantom_fn() {
+    test_one(PhantomData::<fn() -> u32>, &[]);
+    test_one(
+        Marked::<Opaque> {
+            id: 0x2A,
+            _marker: PhantomData,
+        },
+        &[0x2A],
+    );
+}
+
+#[test]
+fn byte_arrays() {
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Signed {
+        nonce: ByteArray<16>,
+        signature: ByteArray<64>,
+        len: u8,
+    }
+
+    let nonce: [u8; 16] = core::array::from_fn(|i| i as u8);
+    let signature: [u8; 64] = core::array::from_fn(|i| 0xFF - i as u8);
+    let signed = Signed {
+        nonce: nonce.into(),
+        signature: ByteArray(signature),
+        len: 80,
+    };
+
+    let mut expected = nonce.to_vec();
+    expected.extend_from_slice(&signature);
+    expected.push(80);
+    assert_eq!(max_size::<(ByteArray<16>, ByteArray<64>, u8)>(), 81);
+    test_one(signed, &expected);
+    test_one(ByteArray::<0>([]), &[]);
+
+    let out: ByteArray<16> = from_bytes(&nonce).unwrap();
+    assert_eq!(<[u8; 16]>::from(out), nonce);
+    assert_eq!(out.as_ref(), &nonce[..]);
+
+    assert_eq!(
+        from_bytes::<ByteArray<64>>(&signature[..63]),
+        Err(Error::DeserializeUnexpectedEnd)
+    );
+}
+
+#[test]
+fn zero_sized_fields() {
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Unit;
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Empty {}
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Foo {
+        a: u8,
+        _m: PhantomData<Opaque>,
+        unit: Unit,
+        empty: Empty,
+        nothing: (),
+        b: u8,
+    }
+
+    test_one(Unit, &[]);
+    test_one(Empty {}, &[]);
+    test_one([Unit, Unit, Unit], &[]);
+    test_one(
+        Foo {
+            a: 0x0A,
+            _m: PhantomData,
+            unit: Unit,
+            empty: Empty {},
+            nothing: (),
+            b: 0x0B,
+        },
+        &[0x0A, 0x0B],
+    );
+}
+
+#[test]
+fn ranges() {
+    // Bounded ranges are structs of their bounds
+    test_one(7u32.., &[0x07, 0x00, 0x00, 0x00]);
+    test_one(..0x1234u32, &[0x34, 0x12, 0x00, 0x00]);
+    test_one(1u8..3, &[0x01, 0x03]);
+    test_one(1u8..=3, &[0x01, 0x03]);
+
+    // Empty and reversed ranges are kept as is
+    test_one(5u32..5, &[0x05, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00]);
+    test_one(
+        RangeInclusive::new(5u32, 4),
+        &[0x05, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00],
+    );
+    test_one(
+        0..u32::MAX,
+        &[0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF],
+    );
+    test_one(
+        u32::MAX..=u32::MAX,
+        &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+    );
+
+    // Only the bounds are written, so whether an inclusive range has been
+    // iterated to the end is lost
+    let mut exhausted = 1u32..=1;
+    exhausted.next();
+    assert!(exhausted.is_empty());
+    let output: Vec<u8> = to_vec(&exhausted).unwrap();
+    let out: RangeInclusive<u32> = from_bytes(&output).unwrap();
+    assert_eq!(out, 1..=1);
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Slicing {
+        head: RangeTo<u32>,
+        #[serde(with = "pinecone::compat::range_full")]
+        rest: RangeFull,
+    }
+    test_one(
+        Slicing {
+            head: ..2,
+            rest: ..,
+        },
+        &[0x02, 0x00, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn c_string() {
+    // Written like a byte slice, without the terminating NUL
+    test_one(CString::default(), &[0x00]);
+    test_one(
+        CString::new("pine").unwrap(),
+        &[0x04, b'p', b'i', b'n', b'e'],
+    );
+
+    let borrowed: &CStr = CStr::from_bytes_with_nul(b"cone\0").unwrap();
+    let output: Vec<u8> = to_vec(borrowed).unwrap();
+    assert_eq!(output, [0x04, b'c', b'o', b'n', b'e']);
+    let out: CString = from_bytes(&output).unwrap();
+    assert_eq!(out.as_bytes_with_nul(), b"cone\0");
+
+    // The NUL check is done by serde's `CString` impl, which reports it
+    // as a custom error
+    assert!(matches!(
+        from_bytes::<CString>(&[0x03, b'a', 0x00, b'b']),
+        Err(Error::SerdeDeCustom(_))
+    ));
+}
+
+#[test]
+fn durations() {
+    // A struct of a u64 and a u32
+    test_one(Duration::ZERO, &[0x00; 12]);
+    test_one(
+        Duration::new(2, 999_999_999),
+        &[2, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xC9, 0x9A, 0x3B],
+    );
+    test_one(
+        Duration::MAX,
+        &[
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xC9, 0x9A, 0x3B,
+        ],
+    );
+
+    // The time since the epoch, like a `Duration`
+    let now = SystemTime::now();
+    let output: Vec<u8> = to_vec(&now).unwrap();
+    assert_eq!(output.len(), 12);
+    assert_eq!(from_bytes::<SystemTime>(&output), Ok(now));
+    test_one(UNIX_EPOCH, &[0x00; 12]);
+}
+
+#[test]
+fn results() {
+    // An enum with `Ok` as variant 0 and `Err` as variant 1
+    test_one(Ok::<u32, String>(0x1234), &[0x00, 0x34, 0x12, 0x00, 0x00]);
+    test_one(
+        Err::<u32, String>("no".to_string()),
+        &[0x01, 0x02, b'n', b'o'],
+    );
+    test_one(Ok::<(), u8>(()), &[0x00]);
+    test_one(Err::<(), u8>(7), &[0x01, 0x07]);
+
+    type Nested = Result<Result<u8, u8>, u8>;
+    test_one::<Nested>(Ok(Ok(1)), &[0x00, 0x00, 0x01]);
+    test_one::<Nested>(Ok(Err(2)), &[0x00, 0x01, 0x02]);
+    test_one::<Nested>(Err(3), &[0x01, 0x03]);
+
+    assert!(matches!(
+        from_bytes::<Result<u8, u8>>(&[0x02, 0x00]),
+        Err(Error::SerdeDeCustom(_))
+    ));
+}
+
+#[test]
+fn sorted_collections() {
+    // Written in key order, whatever order the entries were inserted in
+    let forward: BTreeMap<u32, String> = (1..=3).map(|k| (k, k.to_string())).collect();
+    let backward: BTreeMap<u32, String> = (1..=3).rev().map(|k| (k, k.to_string())).collect();
+    let output: Vec<u8> = to_vec(&backward).unwrap();
+    assert_eq!(output, to_vec(&forward).unwrap());
+    test_one(
+        forward,
+        &[
+            0x03, 0x01, 0x00, 0x00, 0x00, 0x01, b'1', 0x02, 0x00, 0x00, 0x00, 0x01, b'2', 0x03,
+            0x00, 0x00, 0x00, 0x01, b'3',
+        ],
+    );
+
+    let mut set = BTreeSet::new();
+    for value in [0x300u16, 0x1, 0x20, 0x1].iter() {
+        set.insert(*value);
+    }
+    test_one(set, &[0x03, 0x01, 0x00, 0x20, 0x00, 0x00, 0x03]);
+    test_one(BTreeSet::<u16>::new(), &[0x00]);
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn index_map() {
+    // Without `std`, indexmap has no default hasher
+    type IndexMap<K, V> = indexmap::IndexMap<K, V, hashbrown::hash_map::DefaultHashBuilder>;
+
+    // Entries are written in iteration order, and inserted as they are read
+    let mut map: IndexMap<u8, String> = IndexMap::default();
+    map.insert(3, "three".to_string());
+    map.insert(1, "one".to_string());
+    map.insert(2, "two".to_string());
+
+    let output: Vec<u8> = to_vec(&map).unwrap();
+    assert_eq!(&output[..3], &[0x03, 0x03, 0x05]);
+    let out: IndexMap<u8, String> = from_bytes(&output).unwrap();
+    assert!(out.keys().eq([3, 1, 2].iter()));
+    assert!(out.iter().eq(map.iter()));
+
+    test_one(IndexMap::<u8, String>::default(), &[0x00]);
+}
+
+#[cfg(feature = "erased-serde")]
+#[test]
+fn erased() {
+    use pinecone::to_vec_erased;
+
+    let data = DataEnum::Kim(EnumStruct {
+        eight: 0xF0,
+        sixt: 0xACAC,
+    });
+    let erased: &dyn erased_serde::Serialize = &data;
+    let output: Vec<u8> = to_vec_erased(erased).unwrap();
+    assert_eq!(output, to_vec(&data).unwrap());
+    assert_eq!(output, [0x03, 0xF0, 0xAC, 0xAC]);
+
+    // Boxed objects nested in other values are serialized in place
+    let boxed: Vec<Box<dyn erased_serde::Serialize>> = vec![Box::new(data), Box::new(0x1234u16)];
+    let output: Vec<u8> = to_vec(&boxed).unwrap();
+    assert_eq!(output, [0x02, 0x03, 0xF0, 0xAC, 0xAC, 0x34, 0x12]);
+}
+
+#[test]
+fn wide_unit_enum() {
+    test_one(Wide::V0, &[0x00]);
+    test_one(Wide::V127, &[0x7F]);
+    test_one(Wide::V128, &[0x80, 0x01]);
+    test_one(Wide::V199, &[0xC7, 0x01]);
+
+    // One past the last variant
+    assert!(matches!(
+        from_bytes::<Wide>(&[0xC8, 0x01]),
+        Err(Error::SerdeDeCustom(_))
+    ));
+}
+
+#[cfg(feature = "half")]
+#[test]
+fn half_floats() {
+    use half::{bf16, f16};
+
+    let values = [
+        f16::NAN,
+        f16::from_bits(0x7E01), // NaN with a payload
+        f16::INFINITY,
+        f16::NEG_INFINITY,
+        f16::MIN_POSITIVE_SUBNORMAL,
+        f16::from_bits(0x83FF), // Largest negative subnormal
+        f16::NEG_ZERO,
+        f16::ONE,
+    ];
+    for value in values.iter() {
+        let output: Vec<u8> = to_vec(value).unwrap();
+        assert_eq!(output, value.to_bits().to_le_bytes());
+        let out: f16 = from_bytes(&output).unwrap();
+        assert_eq!(out.to_bits(), value.to_bits());
+    }
+
+    let values = [
+        bf16::NAN,
+        bf16::INFINITY,
+        bf16::MIN_POSITIVE_SUBNORMAL,
+        bf16::PI,
+    ];
+    for value in values.iter() {
+        let output: Vec<u8> = to_vec(value).unwrap();
+        assert_eq!(output, value.to_bits().to_le_bytes());
+        let out: bf16 = from_bytes(&output).unwrap();
+        assert_eq!(out.to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn custom_output() {
+    use pinecone::{SerOutput, Serializer};
+
+    /// Counts the bytes written, without storing them
+    struct Counter(usize);
+
+    impl SerOutput for Counter {
+        type Output = usize;
+
+        fn try_extend(&mut self, data: &[u8]) -> Result<(), ()> {
+            self.0 += data.len();
+            Ok(())
+        }
+
+        fn try_push(&mut self, _data: u8) -> Result<(), ()> {
+            self.0 += 1;
+            Ok(())
+        }
+
+        fn release(self) -> Result<usize, ()> {
+            Ok(self.0)
+        }
+    }
+
+    let value = DataEnum::Chi { a: 1, b: 2 };
+    let mut serializer = Serializer::new(Counter(0));
+    serializer.serialize(&value).unwrap();
+    serializer.serialize("four").unwrap();
+    assert_eq!(serializer.release(), Ok(to_vec(&value).unwrap().len() + 5));
 }
 
 fn test_one<T>(data: T, ser_rep: &[u8])
@@ -162,6 +729,7 @@ where
 {
     let serialized: Vec<u8> = to_vec(&data).unwrap();
     assert_eq!(serialized.len(), ser_rep.len());
+    assert_eq!(serialized_size(&data), Ok(serialized.len()));
     let mut x: Vec<u8> = vec![];
     x.extend(serialized.deref().iter().cloned());
     assert_eq!(x, ser_rep);
@@ -176,6 +744,7 @@ where
     T: Serialize + DeserializeOwned + Eq + PartialEq + Debug,
 {
     let serialized: Vec<u8> = to_vec(&data).unwrap();
+    assert_eq!(serialized_size(&data), Ok(serialized.len()));
     let mut x: Vec<u8> = vec![];
     x.extend(serialized.deref().iter().cloned());
     println!("SER {:?}", x);